@@ -2,6 +2,8 @@ use auto_builder::{Message, socket, ws};
 use futures::future::join;
 use notify::event::ModifyKind;
 use notify::{Event, EventHandler, EventKind, RecursiveMode, Watcher};
+use site_config::CONFIG;
+use std::path::Path;
 use std::process::Command;
 use std::time::Instant;
 use tokio::sync::mpsc::{UnboundedSender, channel, unbounded_channel};
@@ -28,20 +30,15 @@ async fn main() -> Result<(), anyhow::Error> {
     Command::new("bash").arg(&sh).spawn()?;
     Command::new(pwd.join("./target/debug/blog")).spawn()?;
 
-    let exclude_files = ["./blog/static/css/index.css"]
+    let blog = pwd.join(Path::new(&CONFIG.paths.blog));
+    let exclude_files = ["static/css/index.css"]
         .iter()
-        .map(|p| pwd.join(p))
+        .map(|p| blog.join(p))
+        .collect::<Vec<_>>();
+    let include_files = ["other_data", "posts", "static", "tailwind", "templates"]
+        .iter()
+        .map(|p| blog.join(p))
         .collect::<Vec<_>>();
-    let include_files = [
-        "./blog/other_data",
-        "./blog/posts",
-        "./blog/static",
-        "./blog/tailwind",
-        "./blog/templates",
-    ]
-    .iter()
-    .map(|p| pwd.join(p))
-    .collect::<Vec<_>>();
 
     let (tx1, _) = tokio::sync::broadcast::channel::<Bytes>(100);
     let (reload_tx, reload_rx) = channel::<Message>(100);
@@ -64,7 +61,11 @@ async fn main() -> Result<(), anyhow::Error> {
                 Ok(event) => {
                     // println!("{:?}", event.paths);
                     // println!("{:?}", event.kind);
-                    if !matches!(event.kind, EventKind::Modify(ModifyKind::Data(_))) {
+                    // removals too, so removed posts leave the search index
+                    if !matches!(
+                        event.kind,
+                        EventKind::Modify(ModifyKind::Data(_)) | EventKind::Remove(_)
+                    ) {
                         // println!("continue");
                         continue;
                     }
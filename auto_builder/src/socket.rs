@@ -1,4 +1,6 @@
 use bitcode::{Decode, Encode};
+use site_config::CONFIG;
+use std::path::Path;
 
 use tokio::io::AsyncWriteExt;
 use tokio::net::TcpStream;
@@ -21,7 +23,7 @@ pub enum SocketMsg {
 }
 
 async fn listen(mut rx: Receiver<Message>) -> std::io::Result<()> {
-    let listener = TcpListener::bind("127.0.0.1:9002").await?;
+    let listener = TcpListener::bind(("127.0.0.1", CONFIG.dev.socket_port)).await?;
     println!("Socket server listening on {}", listener.local_addr()?);
     let mut socket = Socket::new_none();
 
@@ -35,14 +37,7 @@ async fn listen(mut rx: Receiver<Message>) -> std::io::Result<()> {
             res = rx.recv() => {
                 match res {
                     Some(Message::Reload(ins, path)) => {
-                        let msg = SocketMsg::Reload(path.into_iter().filter(|p|p.ends_with("post.md")|| p.ends_with("post.md~")).map(|p| {
-                            if p.ends_with("post.md~"){
-                                p.as_os_str().to_str().unwrap().strip_suffix("~").unwrap().to_string()
-                            }else{
-                                p.into_os_string().into_string().unwrap()
-                            }
-
-                        }).collect());
+                        let msg = SocketMsg::Reload(path.iter().filter_map(|p| post_path(p)).collect());
                         let content = bitcode::encode(&msg);
                         if let Err(e) = socket.write_all(&(content.len() as u32).to_be_bytes()).await {
                             eprintln!("Failed to send data to {}: {}", socket.addr().unwrap(), e);
@@ -86,6 +81,16 @@ async fn listen(mut rx: Receiver<Message>) -> std::io::Result<()> {
     }
 }
 
+/// A changed post or frontmatter, an editor backup (`post.md~`) stands for the file it backs up
+fn post_path(path: &Path) -> Option<String> {
+    let path = path.to_str()?;
+    let path = path.strip_suffix('~').unwrap_or(path);
+    ["post.md", "post_frontmatter.toml"]
+        .iter()
+        .any(|name| Path::new(path).ends_with(name))
+        .then(|| path.to_string())
+}
+
 struct Socket {
     stream: Option<TcpStream>,
 }
@@ -1,6 +1,7 @@
 use std::net::SocketAddr;
 
 use futures::SinkExt;
+use site_config::CONFIG;
 use tokio::{
     net::{TcpListener, TcpStream},
     sync::broadcast::Receiver,
@@ -12,7 +13,7 @@ use tokio_tungstenite::{
 
 pub async fn init(tx: tokio::sync::broadcast::Sender<Bytes>) {
     println!("Hello, I'm server!");
-    let addr = "0.0.0.0:9001";
+    let addr = ("0.0.0.0", CONFIG.dev.ws_port);
     let listener = match TcpListener::bind(addr).await {
         Ok(listener) => listener,
         Err(error) => {
@@ -20,7 +21,7 @@ pub async fn init(tx: tokio::sync::broadcast::Sender<Bytes>) {
             return;
         }
     };
-    println!("listening: {}", addr);
+    println!("listening: {}:{}", addr.0, addr.1);
     loop {
         match listener.accept().await {
             Ok((stream, addr)) => {
@@ -1,6 +1,6 @@
 use std::borrow::Cow;
 
-use actix_web::{HttpResponse, ResponseError};
+use actix_web::{HttpResponse, ResponseError, http::StatusCode};
 use search_utils::errors::SearchError;
 use thiserror::Error;
 
@@ -57,9 +57,11 @@ impl From<SearchError> for RespError {
     fn from(err: SearchError) -> Self {
         match err {
             SearchError::TomlDe(_) | SearchError::Walker(_) => RespError::NotFound,
-            SearchError::IO(_) | SearchError::Tantivy(_) | SearchError::Internal(_) => {
-                RespError::InternalServerError
-            }
+            SearchError::IO(_)
+            | SearchError::Tantivy(_)
+            | SearchError::TomlSer(_)
+            | SearchError::TomlEdit(_)
+            | SearchError::Internal(_) => RespError::InternalServerError,
         }
     }
 }
@@ -99,3 +101,31 @@ impl ResponseError for RespError {
         }
     }
 }
+
+/// Errors of the `/api/` routes, answered with `{"error": ...}` instead of the error pages
+#[derive(Debug, Error)]
+#[error(transparent)]
+pub struct ApiError(#[from] RespError);
+
+impl From<SearchError> for ApiError {
+    fn from(err: SearchError) -> Self {
+        ApiError(err.into())
+    }
+}
+
+impl ResponseError for ApiError {
+    fn status_code(&self) -> StatusCode {
+        match self.0 {
+            RespError::NotFound => StatusCode::NOT_FOUND,
+            RespError::BadRequest => StatusCode::BAD_REQUEST,
+            RespError::InternalServerError | RespError::Custom(_) => {
+                StatusCode::INTERNAL_SERVER_ERROR
+            }
+        }
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        HttpResponse::build(self.status_code())
+            .json(serde_json::json!({ "error": self.0.to_string() }))
+    }
+}
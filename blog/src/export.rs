@@ -0,0 +1,220 @@
+use crate::{
+    TEMPLATES, configure, error_page,
+    handlers::{
+        home_handler::page_count,
+        post_handler::SORT_BY_UPDATED_FRONTMATTERS,
+        rss_handler::{PATH_SEGMENT, tag_feed_url},
+        series_handler::SERIES,
+    },
+    not_found_page,
+    post_store::POST_STORE,
+};
+use actix_web::{App, HttpResponse, body, http::Uri, test, web, web::Bytes};
+use percent_encoding::utf8_percent_encode;
+use search_utils::{blog_path, config::CONFIG, post::FRONTMATTER};
+use std::{
+    collections::BTreeMap,
+    fs, io,
+    path::{Component, Path, PathBuf},
+};
+
+/// A route and the file it is exported to
+struct Target {
+    path: String,
+    file: PathBuf,
+}
+
+impl Target {
+    /// `/a/b` is written to `a/b/index.html`
+    fn page(path: String) -> Self {
+        let file = Path::new(path.trim_start_matches('/')).join("index.html");
+        Target { path, file }
+    }
+
+    fn file(path: String) -> Self {
+        let file = PathBuf::from(path.trim_start_matches('/'));
+        Target { path, file }
+    }
+}
+
+/// Every route serving published content, tag search pages move to `/tags/{tag}/`
+fn targets() -> Vec<Target> {
+    let mut targets: Vec<_> = ["/", "/archives", "/about", "/friends", "/search"]
+        .map(|path| Target::page(path.to_string()))
+        .into();
+    targets.extend((1..=page_count()).map(|n| Target::page(format!("/pages/{n}"))));
+    // post count of every lowercase tag
+    let mut tags = BTreeMap::<String, usize>::new();
+    for fm in SORT_BY_UPDATED_FRONTMATTERS.get().iter() {
+        targets.push(Target::page(format!("/posts/{}", fm.file_name)));
+        targets.push(Target::page(format!("/archives/{}", fm.file_name)));
+        for tag in &fm.tags {
+            *tags.entry(tag.to_lowercase()).or_default() += 1;
+        }
+    }
+    targets.extend(SERIES.get().keys().map(|name| Target {
+        path: format!("/series/{}", encode(name)),
+        file: Path::new("series").join(name).join("index.html"),
+    }));
+    targets.extend(
+        [
+            "/index.xml",
+            "/atom.xml",
+            "/feed.json",
+            "/sitemap.xml",
+            "/robots.txt",
+            "/favicon.ico",
+            "/highlight.css",
+            "/other_data/comment.css",
+        ]
+        .map(|path| Target::file(path.to_string())),
+    );
+    for (tag, count) in &tags {
+        let encoded = encode(tag);
+        for feed in ["index.xml", "atom.xml", "feed.json"] {
+            targets.push(Target {
+                path: tag_feed_url(tag, feed),
+                file: Path::new("tags").join(tag).join(feed),
+            });
+        }
+        targets.push(Target {
+            path: format!("/search?tag={encoded}"),
+            file: Path::new("tags").join(tag).join("index.html"),
+        });
+        for n in 1..=count.div_ceil(CONFIG.pages.search_results_per_page) {
+            targets.push(Target {
+                path: format!("/search?tag={encoded}&page={n}"),
+                file: Path::new("tags")
+                    .join(tag)
+                    .join("pages")
+                    .join(n.to_string())
+                    .join("index.html"),
+            });
+        }
+    }
+    targets
+}
+
+/// A series name or tag as a path segment or query value
+fn encode(value: &str) -> String {
+    utf8_percent_encode(value, PATH_SEGMENT).to_string()
+}
+
+/// Request every target from the routes of [`configure`], without a server,
+/// `visit` gets the body or why the target can not be rendered
+async fn render_targets(
+    mut visit: impl FnMut(&Target, Result<Bytes, String>) -> io::Result<()>,
+) -> io::Result<()> {
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(TEMPLATES.clone()))
+            .configure(configure),
+    )
+    .await;
+    for target in targets() {
+        let uri = match target.path.parse::<Uri>() {
+            Ok(uri) => uri,
+            Err(e) => {
+                visit(&target, Err(format!("invalid uri: {e}")))?;
+                continue;
+            }
+        };
+        let req = test::TestRequest::get().uri(&uri.to_string()).to_request();
+        let resp = test::call_service(&app, req).await;
+        let status = resp.status();
+        let body = if status.is_success() {
+            Ok(test::read_body(resp).await)
+        } else {
+            Err(status.to_string())
+        };
+        visit(&target, body)?;
+    }
+    Ok(())
+}
+
+/// Render the site into `dir`, for a static host
+pub async fn export(dir: &Path) -> io::Result<()> {
+    let mut count = 0;
+    render_targets(|target, body| {
+        let body = body.map_err(|reason| {
+            io::Error::other(format!("Can not render {}: {reason}", target.path))
+        })?;
+        // a tag can be anything, it must not leave the export
+        if !target
+            .file
+            .components()
+            .all(|component| matches!(component, Component::Normal(_)))
+        {
+            return Err(io::Error::other(format!(
+                "Can not export {} to {}",
+                target.path,
+                target.file.display()
+            )));
+        }
+        count += 1;
+        write(&dir.join(&target.file), &body)
+    })
+    .await?;
+
+    let not_found = not_found_page().map_err(io::Error::other)?;
+    write(&dir.join("404.html"), &into_bytes(not_found).await?)?;
+    let internal_error = error_page("Internal Server Error", HttpResponse::InternalServerError())
+        .map_err(io::Error::other)?;
+    write(&dir.join("500.html"), &into_bytes(internal_error).await?)?;
+
+    copy_dir(Path::new(&blog_path!("/static")), &dir.join("static"))?;
+    log::info!("Exported {count} files to {}", dir.display());
+    Ok(())
+}
+
+/// Render every post, drafts included, and every exported route.
+///
+/// Returns the number of failures, which have been logged.
+pub async fn check() -> io::Result<usize> {
+    let mut failures = 0;
+    for name in FRONTMATTER.get().keys() {
+        if let Err(e) = POST_STORE.get(name) {
+            log::error!("Can not render post '{name}': {e}");
+            failures += 1;
+        }
+    }
+    let mut count = 0;
+    render_targets(|target, body| {
+        count += 1;
+        if let Err(reason) = body {
+            log::error!("{}: {reason}", target.path);
+            failures += 1;
+        }
+        Ok(())
+    })
+    .await?;
+    log::info!("Checked {count} routes, {failures} failures");
+    Ok(failures)
+}
+
+async fn into_bytes(resp: HttpResponse) -> io::Result<Bytes> {
+    body::to_bytes(resp.into_body())
+        .await
+        .map_err(|e| io::Error::other(e.to_string()))
+}
+
+fn write(path: &Path, content: &[u8]) -> io::Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(path, content)
+}
+
+fn copy_dir(from: &Path, to: &Path) -> io::Result<()> {
+    fs::create_dir_all(to)?;
+    for entry in fs::read_dir(from)? {
+        let entry = entry?;
+        let target = to.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            copy_dir(&entry.path(), &target)?;
+        } else {
+            fs::copy(entry.path(), target)?;
+        }
+    }
+    Ok(())
+}
@@ -1,14 +1,18 @@
 use crate::{
     CONTEXT,
     errors::{CatError, RespError},
-    handlers::post_handler::{SORT_BY_POSTED_FRONTMATTERS, render_a_post},
+    handlers::post_handler::{SORT_BY_POSTED_FRONTMATTERS, latest_update, render_a_post},
+    page_cache::PAGE_CACHE,
 };
-use actix_web::{HttpResponse, route, web};
+use actix_web::{HttpRequest, HttpResponse, route, web};
 use chrono::Datelike;
 use indexmap::IndexMap;
 use num_derive::FromPrimitive;
 use num_traits::FromPrimitive;
-use search_utils::{lock::Lock, post::FrontMatter};
+use search_utils::{
+    lock::{Lock, Shared},
+    post::FrontMatter,
+};
 use serde::Serialize;
 use std::sync::{Arc, LazyLock};
 use strum_macros::AsRefStr;
@@ -63,8 +67,8 @@ pub type MonthMap = IndexMap<Month, Vec<Arc<FrontMatter>>>;
 pub type ArchiveMonth = Vec<(Month, Vec<Arc<FrontMatter>>)>;
 pub type Archives = Vec<(i32, ArchiveYear)>;
 
-pub static ARCHIVES: LazyLock<Lock<Archives>> = LazyLock::new(|| match init_archives() {
-    Ok(map) => Lock::new(map),
+pub static ARCHIVES: LazyLock<Shared<Archives>> = LazyLock::new(|| match init_archives() {
+    Ok(map) => Shared::new(map),
     Err(e) => {
         log::error!("{e}");
         std::process::exit(1);
@@ -94,26 +98,32 @@ pub fn init_archives() -> Result<Archives, CatError> {
 }
 
 #[route("/archives", method = "GET", method = "HEAD")]
-pub async fn archive(templates: web::Data<Arc<Lock<Tera>>>) -> Result<HttpResponse, RespError> {
-    let mut context = CONTEXT.clone();
-    context.insert("page", "archives");
-    let archives = ARCHIVES.get();
-    context.insert("archives", &**archives);
-    let html = templates
-        .get()
-        .render("archives.html", &context)
-        .inspect_err(|e| log::error!("tera: {e}"))?;
-    Ok(HttpResponse::Ok().content_type("text/html").body(html))
+pub async fn archive(
+    req: HttpRequest,
+    templates: web::Data<Arc<Lock<Tera>>>,
+) -> Result<HttpResponse, RespError> {
+    PAGE_CACHE.respond(&req, "text/html", latest_update(), || {
+        let mut context = CONTEXT.clone();
+        context.insert("page", "archives");
+        let archives = ARCHIVES.get();
+        context.insert("archives", &**archives);
+        Ok(templates
+            .get()
+            .render("archives.html", &context)
+            .inspect_err(|e| log::error!("tera: {e}"))?)
+    })
 }
 
 #[route("/archives/{post_name}", method = "GET", method = "HEAD")]
 pub async fn archive_post(
+    req: HttpRequest,
     templates: web::Data<Arc<Lock<Tera>>>,
     post_name: web::Path<String>,
 ) -> Result<HttpResponse, RespError> {
     render_a_post(
+        &req,
         templates,
-        post_name,
+        &post_name,
         &SORT_BY_POSTED_FRONTMATTERS,
         "/archives",
         "Archives",
@@ -6,6 +6,7 @@ use crate::{
 use actix_web::{HttpResponse, post, route, web};
 use base64::{Engine, prelude::BASE64_URL_SAFE_NO_PAD};
 use search_utils::blog_path;
+use search_utils::config::CONFIG;
 use search_utils::lock::Lock;
 use serde::{Deserialize, Serialize};
 use sha1::Digest;
@@ -74,13 +75,13 @@ fn write_friend_request(value: &FriendRequest) -> Result<(), CatError> {
     let content = toml::to_string_pretty(&value).inspect_err(|e| {
         log::error!("{e}");
     })?;
-    let dir = std::path::Path::new("./friend_requests");
+    let dir = std::path::Path::new(&CONFIG.paths.friend_requests);
     if !fs::exists(dir)? {
         fs::create_dir(dir)?;
     } else if count_files(dir)? > 1000 {
         return Err(CatError::custom("Too many friend requests"));
     }
-    fs::write(format!("./friend_requests/{file_name}.toml"), content).inspect_err(|e| {
+    fs::write(dir.join(format!("{file_name}.toml")), content).inspect_err(|e| {
         log::error!("{e}");
     })?;
     if let Err(e) = send_notification(value) {
@@ -1,10 +1,21 @@
-use crate::{CONTEXT, errors::RespError, handlers::post_handler::SORT_BY_UPDATED_FRONTMATTERS};
-use actix_web::{HttpResponse, route, web};
-use search_utils::lock::Lock;
+use crate::{
+    CONTEXT,
+    errors::RespError,
+    handlers::post_handler::{SORT_BY_UPDATED_FRONTMATTERS, latest_update},
+    page_cache::PAGE_CACHE,
+};
+use actix_web::{HttpRequest, HttpResponse, route, web};
+use search_utils::{config::CONFIG, lock::Lock};
 use std::sync::Arc;
 use tera::{Context, Tera};
 
-const POSTS_PER_PAGE: usize = 5;
+/// Number of pages the published posts span
+pub fn page_count() -> usize {
+    SORT_BY_UPDATED_FRONTMATTERS
+        .get()
+        .len()
+        .div_ceil(CONFIG.pages.posts_per_page)
+}
 
 pub struct PageUtil {
     pub start: usize,
@@ -43,35 +54,42 @@ impl PageUtil {
 }
 
 pub async fn render_page(
+    req: &HttpRequest,
     templates: web::Data<Arc<Lock<Tera>>>,
     page_num: usize,
 ) -> Result<HttpResponse, RespError> {
     let frontmatters = SORT_BY_UPDATED_FRONTMATTERS.get();
-    let page_count = frontmatters.len().div_ceil(POSTS_PER_PAGE);
+    let per_page = CONFIG.pages.posts_per_page;
+    let page_count = frontmatters.len().div_ceil(per_page);
     if page_num > page_count || page_num < 1 {
         return Err(RespError::NotFound);
     }
-    let mut context = CONTEXT.clone();
-    let fms = &frontmatters
-        [(page_num - 1) * POSTS_PER_PAGE..(page_num * POSTS_PER_PAGE).min(frontmatters.len())];
+    PAGE_CACHE.respond(req, "text/html", latest_update(), || {
+        let mut context = CONTEXT.clone();
+        let fms =
+            &frontmatters[(page_num - 1) * per_page..(page_num * per_page).min(frontmatters.len())];
 
-    PageUtil::insert(&mut context, page_num, page_count);
-    context.insert("posts", &fms);
-    context.insert("page", "home");
-    let html = templates.get().render("home.html", &context)?;
-    Ok(HttpResponse::Ok().content_type("text/html").body(html))
+        PageUtil::insert(&mut context, page_num, page_count);
+        context.insert("posts", &fms);
+        context.insert("page", "home");
+        Ok(templates.get().render("home.html", &context)?)
+    })
 }
 
 #[route("/", method = "GET", method = "HEAD")]
-pub async fn index(templates: web::Data<Arc<Lock<Tera>>>) -> Result<HttpResponse, RespError> {
-    render_page(templates, 1).await
+pub async fn index(
+    req: HttpRequest,
+    templates: web::Data<Arc<Lock<Tera>>>,
+) -> Result<HttpResponse, RespError> {
+    render_page(&req, templates, 1).await
 }
 
 #[route("/pages/{page_num}", method = "GET", method = "HEAD")]
 pub async fn page(
+    req: HttpRequest,
     templates: web::Data<Arc<Lock<Tera>>>,
     page_num: web::Path<usize>,
 ) -> Result<HttpResponse, RespError> {
     let page_num = page_num.into_inner();
-    render_page(templates, page_num).await
+    render_page(&req, templates, page_num).await
 }
@@ -3,8 +3,11 @@ pub mod archive_handler;
 mod friend_links_handler;
 pub mod home_handler;
 pub mod post_handler;
-mod rss_handler;
-mod search_handler;
+pub mod preview_handler;
+pub mod rss_handler;
+pub mod search_handler;
+pub mod series_handler;
+mod sitemap_handler;
 
 pub use about_handler::about;
 pub use archive_handler::archive;
@@ -13,6 +16,9 @@ pub use friend_links_handler::{FriendRequest, friend_links, post_link};
 pub use home_handler::index;
 pub use home_handler::page;
 pub use post_handler::post;
+pub use preview_handler::preview;
 pub use rss_handler::favicon;
-pub use rss_handler::rss;
-pub use search_handler::{search, search_lucky};
+pub use rss_handler::{atom, json_feed, rss, tag_atom, tag_json_feed, tag_rss};
+pub use search_handler::{api_search, api_suggest, search, search_lucky};
+pub use series_handler::series;
+pub use sitemap_handler::{robots, sitemap};
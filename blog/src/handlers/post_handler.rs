@@ -1,66 +1,106 @@
-use crate::{CONTEXT, errors::RespError};
-use actix_web::{HttpResponse, route, web};
+use crate::{
+    CONTEXT,
+    errors::RespError,
+    handlers::series_handler::insert_series_nav,
+    page_cache::PAGE_CACHE,
+    post_store::{POST_STORE, post_modified},
+};
+use actix_web::{HttpRequest, HttpResponse, route, web};
+use chrono::{DateTime, Utc};
 use search_utils::{
-    lock::Lock,
-    post::{FRONTMATTER, FrontMatter, MD_OPTIONS, extract_frontmatter, extract_md},
+    lock::{Lock, Shared},
+    post::{FRONTMATTER, FrontMatter, extract_frontmatter},
 };
 use serde::Serialize;
-use std::sync::{Arc, LazyLock};
-use tera::Tera;
+use std::{
+    cmp::Reverse,
+    sync::{Arc, LazyLock},
+    time::SystemTime,
+};
+use tera::{Context, Tera};
 
-pub fn initial_sort_by_posted_fm() -> Vec<Arc<FrontMatter>> {
-    let mut fms = FRONTMATTER
-        .get()
-        .values()
+/// The posts of `frontmatters` that are published at `now`
+pub fn published_at<'a>(
+    frontmatters: impl IntoIterator<Item = &'a Arc<FrontMatter>>,
+    now: DateTime<Utc>,
+) -> Vec<Arc<FrontMatter>> {
+    frontmatters
+        .into_iter()
+        .filter(|fm| fm.is_published_at(now))
         .cloned()
-        .collect::<Vec<Arc<FrontMatter>>>();
-    fms.sort_by(|a, b| b.posted.cmp(&a.posted));
+        .collect()
+}
+
+pub fn initial_sort_by_posted_fm() -> Vec<Arc<FrontMatter>> {
+    let mut fms = published_at(FRONTMATTER.get().values(), Utc::now());
+    fms.sort_by_key(|fm| Reverse(fm.posted));
     fms
 }
 
 pub fn initial_sort_by_updated_fm() -> Vec<Arc<FrontMatter>> {
-    let mut fms = FRONTMATTER
-        .get()
-        .values()
-        .cloned()
-        .collect::<Vec<Arc<FrontMatter>>>();
-    fms.sort_by(|a, b| b.updated.cmp(&a.updated));
+    let mut fms = published_at(FRONTMATTER.get().values(), Utc::now());
+    fms.sort_by_key(|fm| Reverse(fm.updated));
     fms
 }
 
-pub static SORT_BY_POSTED_FRONTMATTERS: LazyLock<Lock<Vec<Arc<FrontMatter>>>> =
-    LazyLock::new(|| Lock::new(initial_sort_by_posted_fm()));
+pub static SORT_BY_POSTED_FRONTMATTERS: LazyLock<Shared<Vec<Arc<FrontMatter>>>> =
+    LazyLock::new(|| Shared::new(initial_sort_by_posted_fm()));
 
-pub static SORT_BY_UPDATED_FRONTMATTERS: LazyLock<Lock<Vec<Arc<FrontMatter>>>> =
-    LazyLock::new(|| Lock::new(initial_sort_by_updated_fm()));
+pub static SORT_BY_UPDATED_FRONTMATTERS: LazyLock<Shared<Vec<Arc<FrontMatter>>>> =
+    LazyLock::new(|| Shared::new(initial_sort_by_updated_fm()));
 
+/// The newest `updated` of the published posts
+pub fn latest_update() -> SystemTime {
+    SORT_BY_UPDATED_FRONTMATTERS
+        .get()
+        .first()
+        .map_or(SystemTime::UNIX_EPOCH, |fm| (*fm.updated).into())
+}
+
+/// A feed item, with the dates formatted for every kind of feed
 #[derive(Debug, Serialize)]
 pub struct FrontMatterWithRfc2822 {
     pub fm: Arc<FrontMatter>,
+    /// RFC 2822 `updated`, for RSS
     pub date: String,
+    /// RFC 3339 `posted`, for Atom and JSON Feed
+    pub published: String,
+    /// RFC 3339 `updated`, for Atom and JSON Feed
+    pub updated: String,
 }
 
-pub static SORT_BY_UPDATED_WITH_RFC2822: LazyLock<Vec<FrontMatterWithRfc2822>> =
-    LazyLock::new(|| {
-        SORT_BY_UPDATED_FRONTMATTERS
-            .get()
-            .iter()
-            .map(|fm| {
-                let fm = fm.clone();
-                let date = fm.updated.to_rfc2822();
-                FrontMatterWithRfc2822 { fm, date }
-            })
-            .collect::<Vec<FrontMatterWithRfc2822>>()
-    });
+pub fn initial_feed_items() -> Vec<FrontMatterWithRfc2822> {
+    SORT_BY_UPDATED_FRONTMATTERS
+        .get()
+        .iter()
+        .map(|fm| {
+            let fm = fm.clone();
+            let date = fm.updated.to_rfc2822();
+            let published = fm.posted.to_rfc3339();
+            let updated = fm.updated.to_rfc3339();
+            FrontMatterWithRfc2822 {
+                fm,
+                date,
+                published,
+                updated,
+            }
+        })
+        .collect::<Vec<FrontMatterWithRfc2822>>()
+}
+
+pub static SORT_BY_UPDATED_WITH_RFC2822: LazyLock<Shared<Vec<FrontMatterWithRfc2822>>> =
+    LazyLock::new(|| Shared::new(initial_feed_items()));
 
 #[route("/posts/{post_name}", method = "GET", method = "HEAD")]
 pub async fn post(
+    req: HttpRequest,
     templates: web::Data<Arc<Lock<Tera>>>,
     post_name: web::Path<String>,
 ) -> Result<HttpResponse, RespError> {
     render_a_post(
+        &req,
         templates,
-        post_name,
+        &post_name,
         &SORT_BY_UPDATED_FRONTMATTERS,
         "/",
         "Home",
@@ -69,23 +109,62 @@ pub async fn post(
 }
 
 pub fn render_a_post(
+    req: &HttpRequest,
     templates: web::Data<Arc<Lock<Tera>>>,
-    post_name: web::Path<String>,
-    fms: &Lock<Vec<Arc<FrontMatter>>>,
+    post_name: &str,
+    fms: &Shared<Vec<Arc<FrontMatter>>>,
     back: &str,
     back_text: &str,
     current: &str,
 ) -> Result<HttpResponse, RespError> {
-    let mut context = CONTEXT.clone();
-    let md_text = extract_md(&post_name).map_err(|e| {
+    let frontmatter = extract_frontmatter(post_name).map_err(|e| {
         log::error!("{e}");
         RespError::NotFound
     })?;
-    let frontmatter = extract_frontmatter(&post_name).inspect_err(|e| log::error!("{e}"))?;
+    if !frontmatter.is_published() {
+        return Err(RespError::NotFound);
+    }
+    PAGE_CACHE.respond(req, "text/html", post_modified(&frontmatter), || {
+        render_post_page(
+            templates,
+            CONTEXT.clone(),
+            &frontmatter,
+            fms,
+            back,
+            back_text,
+            current,
+        )
+    })
+}
 
-    let md_html = comrak::markdown_to_html(&md_text, &MD_OPTIONS);
-    context.insert("post", &md_html);
-    context.insert("meta_data", frontmatter.as_ref());
+pub fn render_post_page(
+    templates: web::Data<Arc<Lock<Tera>>>,
+    mut context: Context,
+    frontmatter: &FrontMatter,
+    fms: &Shared<Vec<Arc<FrontMatter>>>,
+    back: &str,
+    back_text: &str,
+    current: &str,
+) -> Result<String, RespError> {
+    let rendered = POST_STORE
+        .get(&frontmatter.file_name)
+        .inspect_err(|e| log::error!("{e}"))?;
+
+    context.insert("post", &rendered.html);
+    if frontmatter.toc && !rendered.toc.is_empty() {
+        context.insert("toc", &rendered.toc);
+    }
+    let related = POST_STORE
+        .related(frontmatter)
+        .iter()
+        .filter_map(|name| extract_frontmatter(name).ok())
+        .filter(|fm| fm.is_published())
+        .collect::<Vec<_>>();
+    if !related.is_empty() {
+        context.insert("related", &related);
+    }
+    context.insert("meta_data", frontmatter);
+    insert_series_nav(&mut context, frontmatter);
     context.insert("back", back);
     context.insert("back_text", back_text);
     context.insert("current", current);
@@ -102,6 +181,5 @@ pub fn render_a_post(
             context.insert("prev", prev);
         }
     }
-    let html = templates.get().render("post.html", &context)?;
-    Ok(HttpResponse::Ok().content_type("text/html").body(html))
+    Ok(templates.get().render("post.html", &context)?)
 }
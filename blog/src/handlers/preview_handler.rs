@@ -0,0 +1,106 @@
+use crate::{
+    CONTEXT,
+    errors::RespError,
+    handlers::post_handler::{SORT_BY_UPDATED_FRONTMATTERS, render_post_page},
+};
+use actix_web::{HttpResponse, route, web};
+use base64::{Engine, prelude::BASE64_URL_SAFE_NO_PAD};
+use search_utils::{
+    lock::Lock,
+    post::{FRONTMATTER, extract_frontmatter},
+};
+use sha1::Digest;
+use std::sync::{Arc, LazyLock};
+use subtle::ConstantTimeEq;
+use tera::Tera;
+
+/// Previews are disabled when this env var is unset or empty
+pub const PREVIEW_SECRET_ENV: &str = "BLOG_PREVIEW_SECRET";
+
+static PREVIEW_SECRET: LazyLock<Option<String>> = LazyLock::new(|| {
+    std::env::var(PREVIEW_SECRET_ENV)
+        .ok()
+        .filter(|s| !s.is_empty())
+});
+
+/// HMAC-SHA1 (RFC 2104) of the post name keyed with the secret
+fn preview_token(secret: &str, post_name: &str) -> String {
+    const BLOCK_SIZE: usize = 64;
+    let mut key = [0u8; BLOCK_SIZE];
+    if secret.len() > BLOCK_SIZE {
+        let digest = sha1::Sha1::digest(secret.as_bytes());
+        key[..digest.len()].copy_from_slice(&digest);
+    } else {
+        key[..secret.len()].copy_from_slice(secret.as_bytes());
+    }
+    let inner = sha1::Sha1::new()
+        .chain_update(key.map(|b| b ^ 0x36))
+        .chain_update(post_name.as_bytes())
+        .finalize();
+    let outer = sha1::Sha1::new()
+        .chain_update(key.map(|b| b ^ 0x5c))
+        .chain_update(inner)
+        .finalize();
+    BASE64_URL_SAFE_NO_PAD.encode(outer)
+}
+
+/// Log the preview urls of drafts and scheduled posts
+pub fn log_preview_urls() {
+    let Some(secret) = PREVIEW_SECRET.as_deref() else {
+        return;
+    };
+    for fm in FRONTMATTER.get().values() {
+        if !fm.is_published() {
+            log::info!(
+                "Preview '{}': /preview/{}/{}",
+                fm.title,
+                fm.file_name,
+                preview_token(secret, &fm.file_name)
+            );
+        }
+    }
+}
+
+#[route("/preview/{post_name}/{token}", method = "GET", method = "HEAD")]
+pub async fn preview(
+    templates: web::Data<Arc<Lock<Tera>>>,
+    path: web::Path<(String, String)>,
+) -> Result<HttpResponse, RespError> {
+    let (post_name, token) = path.into_inner();
+    let secret = PREVIEW_SECRET.as_deref().ok_or(RespError::NotFound)?;
+    // a token is not leaked by how long it takes to reject a guess
+    let expected = preview_token(secret, &post_name);
+    if !bool::from(token.as_bytes().ct_eq(expected.as_bytes())) {
+        return Err(RespError::NotFound);
+    }
+    let frontmatter = extract_frontmatter(&post_name).map_err(|e| {
+        log::error!("{e}");
+        RespError::NotFound
+    })?;
+    let mut context = CONTEXT.clone();
+    context.insert("preview", &true);
+    let html = render_post_page(
+        templates,
+        context,
+        &frontmatter,
+        &SORT_BY_UPDATED_FRONTMATTERS,
+        "/",
+        "Home",
+        "/posts",
+    )?;
+    Ok(HttpResponse::Ok().content_type("text/html").body(html))
+}
+
+#[test]
+fn test_preview_token() {
+    // RFC 2202 test case 2
+    assert_eq!(
+        preview_token("Jefe", "what do ya want for nothing?"),
+        "7_zfauXrL6LSdBbV8YTfnCWafHk"
+    );
+    // keys longer than a block are hashed first
+    assert_eq!(
+        preview_token(&"k".repeat(100), "post"),
+        "Gor67LOL7VppXRUfzqF-9HzuvfQ"
+    );
+}
@@ -1,39 +1,303 @@
-use actix_web::{HttpResponse, route, web};
-use search_utils::{
-    blog_path,
-    lock::Lock,
-    post::{MD_OPTIONS, extract_md},
-};
-use std::sync::Arc;
+use actix_web::{HttpRequest, HttpResponse, http::header, route, web};
+use percent_encoding::{AsciiSet, NON_ALPHANUMERIC, utf8_percent_encode};
+use search_utils::{blog_path, config::CONFIG, lock::Lock};
+use serde::Serialize;
+use std::{sync::Arc, time::SystemTime};
 use tera::Tera;
 
 use crate::{
     CONTEXT,
     errors::{CatError, RespError},
-    handlers::post_handler::SORT_BY_UPDATED_WITH_RFC2822,
+    handlers::{
+        post_handler::{FrontMatterWithRfc2822, SORT_BY_UPDATED_WITH_RFC2822},
+        search_handler::ALL_TAGS,
+    },
+    page_cache::PAGE_CACHE,
+    post_store::{POST_STORE, RenderedPost, post_modified},
 };
 
-#[route("/index.xml", method = "GET", method = "HEAD")]
-pub async fn rss(templates: web::Data<Arc<Lock<Tera>>>) -> Result<HttpResponse, RespError> {
+fn rendered_posts(items: &[&FrontMatterWithRfc2822]) -> Result<Vec<Arc<RenderedPost>>, RespError> {
+    let mut rendered = Vec::with_capacity(items.len());
+    for item in items {
+        rendered.push(POST_STORE.get(&item.fm.file_name)?);
+    }
+    Ok(rendered)
+}
+
+/// Escaped like `encodeURIComponent`
+pub const PATH_SEGMENT: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'-')
+    .remove(b'_')
+    .remove(b'.')
+    .remove(b'~');
+
+/// Path of a tag feed, the tag is lowercased like the tag pages
+pub fn tag_feed_url(tag: &str, feed: &str) -> String {
+    let tag = tag.to_lowercase();
+    format!("/tags/{}/{feed}", utf8_percent_encode(&tag, PATH_SEGMENT))
+}
+
+/// The feed of a tag carried by the published posts
+struct TagFeed {
+    /// as the posts spell it
+    tag: String,
+    url: String,
+}
+
+impl TagFeed {
+    /// `tag` is matched case-insensitively, a tag without posts is unknown
+    fn new(tag: &str, feed: &str) -> Result<Self, RespError> {
+        let tag = tag.to_lowercase();
+        let tag = ALL_TAGS
+            .get()
+            .iter()
+            .find(|t| t.to_lowercase() == tag)
+            .ok_or(RespError::NotFound)?
+            .clone();
+        let url = tag_feed_url(&tag, feed);
+        Ok(Self { tag, url })
+    }
+
+    /// Other spellings of the path are sent to [`TagFeed::url`], so that one feed is cached
+    fn redirect(&self, req: &HttpRequest) -> Option<HttpResponse> {
+        (req.path() != self.url).then(|| {
+            HttpResponse::MovedPermanently()
+                .insert_header((header::LOCATION, self.url.as_str()))
+                .finish()
+        })
+    }
+}
+
+/// Items carrying `tag`, matched case-insensitively
+fn tagged_items<'a>(
+    items: &'a [FrontMatterWithRfc2822],
+    tag: &str,
+) -> Result<Vec<&'a FrontMatterWithRfc2822>, RespError> {
+    let tag = tag.to_lowercase();
+    let tagged = items
+        .iter()
+        .filter(|item| item.fm.tags.iter().any(|t| t.to_lowercase() == tag))
+        .collect::<Vec<_>>();
+    if tagged.is_empty() {
+        return Err(RespError::NotFound);
+    }
+    Ok(tagged)
+}
+
+/// Feeds carry the content of their posts, so an edit without a new `updated` counts too
+fn feed_last_modified(items: &[&FrontMatterWithRfc2822]) -> SystemTime {
+    items
+        .iter()
+        .map(|item| post_modified(&item.fm))
+        .max()
+        .unwrap_or(SystemTime::UNIX_EPOCH)
+}
+
+/// Serve a feed from the page cache, rendering it on a miss
+fn cached_feed(
+    req: &HttpRequest,
+    content_type: &'static str,
+    items: &[&FrontMatterWithRfc2822],
+    render: impl FnOnce() -> Result<String, RespError>,
+) -> Result<HttpResponse, RespError> {
+    PAGE_CACHE.respond(req, content_type, feed_last_modified(items), render)
+}
+
+const RSS_TYPE: &str = "text/xml; charset=utf-8";
+const ATOM_TYPE: &str = "application/atom+xml; charset=utf-8";
+const JSON_FEED_TYPE: &str = "application/feed+json; charset=utf-8";
+
+/// Render an RSS or Atom template, `feed_url` is the path of the feed itself
+fn render_xml_feed(
+    templates: &Lock<Tera>,
+    template: &str,
+    items: &[&FrontMatterWithRfc2822],
+    feed_url: &str,
+    tag: Option<&str>,
+) -> Result<String, RespError> {
     let mut context = CONTEXT.clone();
-    if let Some(first) = SORT_BY_UPDATED_WITH_RFC2822.first() {
-        context.insert("latest_update", first.date.as_str());
+    if let Some(tag) = tag {
+        context.insert("tag", tag);
     }
-    let mut contents = Vec::new();
-    for item in SORT_BY_UPDATED_WITH_RFC2822.iter() {
-        let md_text = extract_md(&item.fm.file_name)?;
-        let md = comrak::markdown_to_html(&md_text, &MD_OPTIONS);
-        contents.push(md);
+    if let Some(first) = items.first() {
+        context.insert("latest_update", first.date.as_str());
+        context.insert("latest_updated", first.updated.as_str());
     }
-    context.insert("posts", &*SORT_BY_UPDATED_WITH_RFC2822);
+    let rendered = rendered_posts(items)?;
+    let contents = rendered.iter().map(|p| p.feed_html()).collect::<Vec<_>>();
+    context.insert("posts", items);
     context.insert("contents", &contents);
-    let html = templates
+    context.insert("feed_url", feed_url);
+    Ok(templates
         .get()
-        .render("rss.xml", &context)
-        .inspect_err(|e| log::error!("{e}"))?;
-    Ok(HttpResponse::Ok()
-        .content_type("text/xml; charset=utf-8")
-        .body(html))
+        .render(template, &context)
+        .inspect_err(|e| log::error!("{e}"))?)
+}
+
+/// JSON Feed 1.1, see <https://www.jsonfeed.org/version/1.1/>
+#[derive(Debug, Serialize)]
+struct JsonFeed<'a> {
+    version: &'static str,
+    title: String,
+    home_page_url: String,
+    feed_url: String,
+    language: &'static str,
+    items: Vec<JsonFeedItem<'a>>,
+}
+
+#[derive(Debug, Serialize)]
+struct JsonFeedItem<'a> {
+    id: String,
+    url: String,
+    title: &'a str,
+    content_html: &'a str,
+    summary: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    image: Option<String>,
+    date_published: &'a str,
+    date_modified: &'a str,
+    authors: [JsonFeedAuthor<'a>; 1],
+    tags: &'a [String],
+}
+
+#[derive(Debug, Serialize)]
+struct JsonFeedAuthor<'a> {
+    name: &'a str,
+}
+
+fn absolute_url(path: &str) -> String {
+    if path.starts_with("http://") || path.starts_with("https://") {
+        path.to_string()
+    } else {
+        format!("{}/{}", CONFIG.site.url, path.trim_start_matches('/'))
+    }
+}
+
+fn render_json_feed(
+    items: &[&FrontMatterWithRfc2822],
+    feed_url: &str,
+    tag: Option<&str>,
+) -> Result<String, RespError> {
+    let rendered = rendered_posts(items)?;
+    let items = items
+        .iter()
+        .zip(rendered.iter())
+        .map(|(item, post)| {
+            let url = format!("{}/posts/{}", CONFIG.site.url, item.fm.file_name);
+            JsonFeedItem {
+                id: url.clone(),
+                url,
+                title: &item.fm.title,
+                content_html: post.feed_html(),
+                summary: &item.fm.description,
+                image: item.fm.cover_image.as_ref().map(|img| absolute_url(img)),
+                date_published: &item.published,
+                date_modified: &item.updated,
+                authors: [JsonFeedAuthor {
+                    name: &item.fm.author,
+                }],
+                tags: &item.fm.tags,
+            }
+        })
+        .collect();
+    let feed = JsonFeed {
+        version: "https://jsonfeed.org/version/1.1",
+        title: match tag {
+            Some(tag) => format!("{} - {tag}", CONFIG.site.title),
+            None => CONFIG.site.title.clone(),
+        },
+        home_page_url: format!("{}/", CONFIG.site.url),
+        feed_url: format!("{}{feed_url}", CONFIG.site.url),
+        language: "zh-CN",
+        items,
+    };
+    serde_json::to_string(&feed).map_err(|e| {
+        log::error!("{e}");
+        RespError::InternalServerError
+    })
+}
+
+#[route("/index.xml", method = "GET", method = "HEAD")]
+pub async fn rss(
+    req: HttpRequest,
+    templates: web::Data<Arc<Lock<Tera>>>,
+) -> Result<HttpResponse, RespError> {
+    let items = SORT_BY_UPDATED_WITH_RFC2822.get();
+    let items = items.iter().collect::<Vec<_>>();
+    cached_feed(&req, RSS_TYPE, &items, || {
+        render_xml_feed(&templates, "rss.xml", &items, "/index.xml", None)
+    })
+}
+
+#[route("/atom.xml", method = "GET", method = "HEAD")]
+pub async fn atom(
+    req: HttpRequest,
+    templates: web::Data<Arc<Lock<Tera>>>,
+) -> Result<HttpResponse, RespError> {
+    let items = SORT_BY_UPDATED_WITH_RFC2822.get();
+    let items = items.iter().collect::<Vec<_>>();
+    cached_feed(&req, ATOM_TYPE, &items, || {
+        render_xml_feed(&templates, "atom.xml", &items, "/atom.xml", None)
+    })
+}
+
+#[route("/feed.json", method = "GET", method = "HEAD")]
+pub async fn json_feed(req: HttpRequest) -> Result<HttpResponse, RespError> {
+    let items = SORT_BY_UPDATED_WITH_RFC2822.get();
+    let items = items.iter().collect::<Vec<_>>();
+    cached_feed(&req, JSON_FEED_TYPE, &items, || {
+        render_json_feed(&items, "/feed.json", None)
+    })
+}
+
+#[route("/tags/{tag}/index.xml", method = "GET", method = "HEAD")]
+pub async fn tag_rss(
+    req: HttpRequest,
+    templates: web::Data<Arc<Lock<Tera>>>,
+    tag: web::Path<String>,
+) -> Result<HttpResponse, RespError> {
+    let feed = TagFeed::new(&tag, "index.xml")?;
+    if let Some(redirect) = feed.redirect(&req) {
+        return Ok(redirect);
+    }
+    let items = SORT_BY_UPDATED_WITH_RFC2822.get();
+    let items = tagged_items(items.as_slice(), &feed.tag)?;
+    cached_feed(&req, RSS_TYPE, &items, || {
+        render_xml_feed(&templates, "rss.xml", &items, &feed.url, Some(&feed.tag))
+    })
+}
+
+#[route("/tags/{tag}/atom.xml", method = "GET", method = "HEAD")]
+pub async fn tag_atom(
+    req: HttpRequest,
+    templates: web::Data<Arc<Lock<Tera>>>,
+    tag: web::Path<String>,
+) -> Result<HttpResponse, RespError> {
+    let feed = TagFeed::new(&tag, "atom.xml")?;
+    if let Some(redirect) = feed.redirect(&req) {
+        return Ok(redirect);
+    }
+    let items = SORT_BY_UPDATED_WITH_RFC2822.get();
+    let items = tagged_items(items.as_slice(), &feed.tag)?;
+    cached_feed(&req, ATOM_TYPE, &items, || {
+        render_xml_feed(&templates, "atom.xml", &items, &feed.url, Some(&feed.tag))
+    })
+}
+
+#[route("/tags/{tag}/feed.json", method = "GET", method = "HEAD")]
+pub async fn tag_json_feed(
+    req: HttpRequest,
+    tag: web::Path<String>,
+) -> Result<HttpResponse, RespError> {
+    let feed = TagFeed::new(&tag, "feed.json")?;
+    if let Some(redirect) = feed.redirect(&req) {
+        return Ok(redirect);
+    }
+    let items = SORT_BY_UPDATED_WITH_RFC2822.get();
+    let items = tagged_items(items.as_slice(), &feed.tag)?;
+    cached_feed(&req, JSON_FEED_TYPE, &items, || {
+        render_json_feed(&items, &feed.url, Some(&feed.tag))
+    })
 }
 
 #[route("/favicon.ico", method = "GET", method = "HEAD")]
@@ -42,3 +306,44 @@ pub async fn favicon() -> Result<HttpResponse, RespError> {
         .map_err(|e| -> CatError { e.into() })?;
     Ok(HttpResponse::Ok().content_type("image/x-icon").body(data))
 }
+
+#[test]
+#[allow(clippy::unwrap_used)]
+fn test_tag_feeds() {
+    use actix_web::{App, http::StatusCode, test};
+
+    crate::test_site::site();
+    assert_eq!(
+        tag_feed_url("C++ & Rust", "index.xml"),
+        "/tags/c%2B%2B%20%26%20rust/index.xml"
+    );
+    actix_web::rt::System::new().block_on(async {
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(crate::TEMPLATES.clone()))
+                .service(tag_rss)
+                .service(tag_atom)
+                .service(tag_json_feed),
+        )
+        .await;
+        let get = |uri: &str| test::TestRequest::get().uri(uri).to_request();
+
+        // every spelling of a tag leads to one feed
+        let resp = test::call_service(&app, get("/tags/RUST/index.xml")).await;
+        assert_eq!(resp.status(), StatusCode::MOVED_PERMANENTLY);
+        assert_eq!(
+            resp.headers().get("location").unwrap(),
+            "/tags/rust/index.xml"
+        );
+        let resp = test::call_service(&app, get("/tags/%72ust/feed.json")).await;
+        assert_eq!(resp.status(), StatusCode::MOVED_PERMANENTLY);
+
+        let body = test::call_and_read_body(&app, get("/tags/rust/atom.xml")).await;
+        let body = String::from_utf8(body.to_vec()).unwrap();
+        assert!(body.contains(" - Rust</title>"));
+        assert!(body.contains(&format!("{}/tags/rust/atom.xml", CONFIG.site.url)));
+
+        let resp = test::call_service(&app, get("/tags/unknown/feed.json")).await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    });
+}
@@ -1,6 +1,6 @@
 use crate::{
     CONTEXT,
-    errors::RespError,
+    errors::{ApiError, RespError},
     handlers::{
         home_handler::PageUtil,
         post_handler::{SORT_BY_POSTED_FRONTMATTERS, SORT_BY_UPDATED_FRONTMATTERS},
@@ -8,16 +8,23 @@ use crate::{
 };
 use actix_web::{HttpRequest, HttpResponse, route, web};
 use rand::seq::IndexedRandom;
-use search_utils::{lock::Lock, post::FrontMatter, search::search_index};
-use serde::{Deserialize, de};
+use search_utils::{
+    config::CONFIG,
+    lock::{Lock, Shared},
+    post::FrontMatter,
+    search::{SearchResult, SearchTerm, Suggestion, search_index, suggest_terms},
+};
+use serde::{Deserialize, Serialize, de};
 use std::{
     borrow::Cow,
-    collections::HashSet,
+    cmp::Reverse,
+    collections::{HashMap, HashSet},
     sync::{Arc, LazyLock},
 };
 use tera::Tera;
 
-const SEARCH_RESULTS_PER_PAGE: usize = 7;
+/// Number of suggestions answered by `/api/suggest`
+const SUGGESTIONS: usize = 10;
 
 #[derive(Debug, Deserialize)]
 #[serde(deny_unknown_fields)]
@@ -61,7 +68,9 @@ where
     }
 }
 
-static ALL_TAGS: LazyLock<Vec<String>> = LazyLock::new(|| {
+pub static ALL_TAGS: LazyLock<Shared<Vec<String>>> = LazyLock::new(|| Shared::new(init_tags()));
+
+pub fn init_tags() -> Vec<String> {
     let frontmatters = SORT_BY_POSTED_FRONTMATTERS.get();
     let mut tags = HashSet::new();
     for fm in frontmatters.iter() {
@@ -70,7 +79,7 @@ static ALL_TAGS: LazyLock<Vec<String>> = LazyLock::new(|| {
     let mut tags = tags.into_iter().collect::<Vec<_>>();
     tags.sort_by_key(|t| t.to_lowercase());
     tags
-});
+}
 
 fn filter_tags(tags: &HashSet<String>) -> Vec<Arc<FrontMatter>> {
     let fm = SORT_BY_UPDATED_FRONTMATTERS.get();
@@ -103,7 +112,7 @@ fn handle_tag(
     let ins = std::time::Instant::now();
     // search by only tags is unstable, their order is not guaranteed
     let result = filter_tags(&tags);
-    let page_count = result.len().div_ceil(SEARCH_RESULTS_PER_PAGE);
+    let page_count = result.len().div_ceil(CONFIG.pages.search_results_per_page);
     if page > page_count {
         if page_count > 0 {
             return Err(RespError::NotFound);
@@ -116,13 +125,13 @@ fn handle_tag(
         context.insert("time_cost", &time_cost);
     }
 
-    let render_result = &result
-        [(page - 1) * SEARCH_RESULTS_PER_PAGE..(page * SEARCH_RESULTS_PER_PAGE).min(result.len())];
+    let render_result = &result[(page - 1) * CONFIG.pages.search_results_per_page
+        ..(page * CONFIG.pages.search_results_per_page).min(result.len())];
 
     context.insert("tag_result", &render_result);
     context.insert("page", "search");
     context.insert("query_param", &query_param);
-    context.insert("all_tags", &*ALL_TAGS);
+    context.insert("all_tags", &*ALL_TAGS.get());
     let html = templates.get().render("search_text.html", &context)?;
     Ok(HttpResponse::Ok().content_type("text/html").body(html))
 }
@@ -144,15 +153,17 @@ fn handle_query_text(
     let search_result = search_index(
         &query_text,
         tags.as_ref(),
-        SEARCH_RESULTS_PER_PAGE,
-        (page - 1) * SEARCH_RESULTS_PER_PAGE,
+        CONFIG.pages.search_results_per_page,
+        (page - 1) * CONFIG.pages.search_results_per_page,
     )
     .inspect_err(|e| log::error!("{e}"))?;
 
     let time_cost = search_result.time_cost.as_secs_f64();
     let time_cost = (time_cost * 1000.0).round() / 1000.0;
 
-    let page_count = search_result.count.div_ceil(SEARCH_RESULTS_PER_PAGE);
+    let page_count = search_result
+        .count
+        .div_ceil(CONFIG.pages.search_results_per_page);
     if page > page_count {
         if page_count > 0 {
             return Err(RespError::NotFound);
@@ -168,7 +179,7 @@ fn handle_query_text(
     context.insert("page", "search");
     context.insert("search_result", &search_result.terms);
     context.insert("query_param", &query_param);
-    context.insert("all_tags", &*ALL_TAGS);
+    context.insert("all_tags", &*ALL_TAGS.get());
 
     let html = templates.get().render("search_text.html", &context)?;
     Ok(HttpResponse::Ok().content_type("text/html").body(html))
@@ -220,7 +231,7 @@ fn search_inner(
         (None, None) => {
             let mut context = CONTEXT.clone();
             context.insert("page", "search");
-            context.insert("all_tags", &*ALL_TAGS);
+            context.insert("all_tags", &*ALL_TAGS.get());
             let html = templates.get().render("search_text.html", &context)?;
             Ok(HttpResponse::Ok().content_type("text/html").body(html))
         }
@@ -268,3 +279,255 @@ pub async fn search_lucky(
             .finish()),
     }
 }
+
+#[derive(Debug, Serialize)]
+struct ApiSearchResult<'a> {
+    count: usize,
+    /// seconds
+    time_cost: f64,
+    page: usize,
+    page_count: usize,
+    results: &'a [SearchTerm],
+}
+
+/// The posts carrying every tag, like the tag search page, their description is
+/// the snippet and they are not ranked
+fn tagged_terms(tags: &HashSet<String>, page: usize, per_page: usize) -> SearchResult<SearchTerm> {
+    let ins = std::time::Instant::now();
+    let tagged = filter_tags(tags);
+    let terms = tagged
+        .iter()
+        .skip((page - 1) * per_page)
+        .take(per_page)
+        .map(|fm| SearchTerm {
+            score: 0.0,
+            fm: fm.clone(),
+            snippet: tera::escape_html(&fm.description),
+        })
+        .collect();
+    SearchResult {
+        count: tagged.len(),
+        time_cost: ins.elapsed(),
+        terms,
+    }
+}
+
+/// Search for the search-as-you-type overlay, `tag` alone lists the tagged posts and
+/// without either nothing matches
+#[route("/api/search", method = "GET", method = "HEAD")]
+pub async fn api_search(
+    query: Result<web::Query<QueryParam>, actix_web::Error>,
+) -> Result<HttpResponse, ApiError> {
+    let query = query
+        .inspect_err(|e| log::info!("bad search query: {e}"))
+        .map_err(|_| RespError::BadRequest)?
+        .into_inner();
+    let page = query.page.unwrap_or(1);
+    if page < 1 {
+        return Err(RespError::BadRequest.into());
+    }
+    let per_page = CONFIG.pages.search_results_per_page;
+    let search_result = match (&query.q, &query.tag) {
+        (Some(query_text), tags) => {
+            search_index(query_text, tags.as_ref(), per_page, (page - 1) * per_page)
+                .inspect_err(|e| log::error!("{e}"))?
+        }
+        (None, Some(tags)) => tagged_terms(tags, page, per_page),
+        (None, None) => Default::default(),
+    };
+    let time_cost = search_result.time_cost.as_secs_f64();
+    let time_cost = (time_cost * 1000.0).round() / 1000.0;
+    Ok(HttpResponse::Ok().json(ApiSearchResult {
+        count: search_result.count,
+        time_cost,
+        page,
+        page_count: search_result.count.div_ceil(per_page),
+        results: &search_result.terms,
+    }))
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SuggestParam {
+    #[serde(deserialize_with = "deserialize_str", default)]
+    prefix: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+enum SuggestionKind {
+    Tag,
+    Title,
+    Term,
+}
+
+#[derive(Debug, Serialize)]
+struct ApiSuggestion {
+    text: String,
+    kind: SuggestionKind,
+    /// number of posts matching the suggestion
+    doc_freq: u32,
+}
+
+impl ApiSuggestion {
+    /// Smaller is better, tags go before titles and terms as frequent as them
+    fn rank(&self) -> (Reverse<u32>, SuggestionKind) {
+        (Reverse(self.doc_freq), self.kind)
+    }
+}
+
+/// Titles containing `prefix` and tags starting with it, case insensitive
+fn suggest_posts(prefix: &str) -> Vec<ApiSuggestion> {
+    let prefix = prefix.to_lowercase();
+    let frontmatters = SORT_BY_POSTED_FRONTMATTERS.get();
+    let mut suggestions = frontmatters
+        .iter()
+        .filter(|fm| fm.title.to_lowercase().contains(&prefix))
+        .map(|fm| ApiSuggestion {
+            text: fm.title.clone(),
+            kind: SuggestionKind::Title,
+            doc_freq: 1,
+        })
+        .collect::<Vec<_>>();
+    for tag in ALL_TAGS
+        .get()
+        .iter()
+        .filter(|tag| tag.to_lowercase().starts_with(&prefix))
+    {
+        let doc_freq = frontmatters
+            .iter()
+            .filter(|fm| fm.tags.iter().any(|t| t.eq_ignore_ascii_case(tag)))
+            .count();
+        suggestions.push(ApiSuggestion {
+            text: tag.clone(),
+            kind: SuggestionKind::Tag,
+            doc_freq: doc_freq as u32,
+        });
+    }
+    suggestions
+}
+
+/// Completions of the search box, from the index terms, post titles and tags,
+/// the most frequent first
+#[route("/api/suggest", method = "GET", method = "HEAD")]
+pub async fn api_suggest(
+    query: Result<web::Query<SuggestParam>, actix_web::Error>,
+) -> Result<HttpResponse, ApiError> {
+    let query = query
+        .inspect_err(|e| log::info!("bad suggest query: {e}"))
+        .map_err(|_| RespError::BadRequest)?
+        .into_inner();
+    let prefix = query.prefix.as_deref().unwrap_or_default().trim_start();
+    if prefix.is_empty() {
+        return Ok(HttpResponse::Ok().json(Vec::<ApiSuggestion>::new()));
+    }
+    let terms = suggest_terms(prefix, SUGGESTIONS).inspect_err(|e| log::error!("{e}"))?;
+    let terms = terms
+        .into_iter()
+        .map(|Suggestion { text, doc_freq }| ApiSuggestion {
+            text,
+            kind: SuggestionKind::Term,
+            doc_freq,
+        });
+    // the same text may be a tag, a title and a term, the most frequent one is kept
+    let mut unique = HashMap::<String, ApiSuggestion>::new();
+    for suggestion in suggest_posts(prefix).into_iter().chain(terms) {
+        match unique.get(&suggestion.text.to_lowercase()) {
+            Some(kept) if kept.rank() <= suggestion.rank() => (),
+            _ => {
+                unique.insert(suggestion.text.to_lowercase(), suggestion);
+            }
+        }
+    }
+    let mut suggestions = unique.into_values().collect::<Vec<_>>();
+    suggestions.sort_by(|a, b| (a.rank(), &a.text).cmp(&(b.rank(), &b.text)));
+    suggestions.truncate(SUGGESTIONS);
+    Ok(HttpResponse::Ok().json(suggestions))
+}
+
+#[test]
+#[allow(clippy::unwrap_used)]
+fn test_api_search() {
+    use actix_web::{App, http::StatusCode, test};
+
+    crate::test_site::site();
+    search_utils::search::reload_index().unwrap();
+    actix_web::rt::System::new().block_on(async {
+        let app = test::init_service(App::new().service(api_search)).await;
+        let get = async |query: &str| {
+            let req = test::TestRequest::get()
+                .uri(&format!("/api/search{query}"))
+                .to_request();
+            let resp = test::call_service(&app, req).await;
+            let status = resp.status();
+            let content_type = resp.headers().get("content-type").unwrap().clone();
+            assert_eq!(content_type, "application/json");
+            let body: serde_json::Value = test::read_body_json(resp).await;
+            (status, body)
+        };
+        for bad in ["?page=0", "?q=rust&page=abc", "?q=rust&unknown=1"] {
+            let (status, body) = get(bad).await;
+            assert_eq!(status, StatusCode::BAD_REQUEST, "{bad}");
+            assert!(body["error"].is_string(), "{bad}");
+        }
+        let (status, body) = get("").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["count"], 0);
+        assert_eq!(body["results"], serde_json::json!([]));
+        // tags alone list the tagged posts, like the tag search page
+        let (status, body) = get("?tag=rust").await;
+        assert_eq!(status, StatusCode::OK);
+        let tagged = filter_tags(&HashSet::from(["rust".to_string()]));
+        assert_eq!(body["count"], tagged.len());
+        let results = body["results"].as_array().unwrap();
+        assert_eq!(
+            results.len(),
+            tagged.len().min(CONFIG.pages.search_results_per_page)
+        );
+        assert_eq!(results[0]["fm"]["file_name"], tagged[0].file_name);
+        let (status, body) = get("?q=rust&tag=Rust").await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(body["count"].as_u64().unwrap() > 0);
+        assert_eq!(body["page"], 1);
+        let first = &body["results"][0];
+        assert!(first["score"].as_f64().unwrap() > 0.0);
+        assert!(first["fm"]["file_name"].is_string());
+        assert!(
+            first["fm"]["tags"]
+                .as_array()
+                .unwrap()
+                .contains(&"Rust".into())
+        );
+        assert!(first["snippet"].is_string());
+    });
+}
+
+#[test]
+#[allow(clippy::unwrap_used)]
+fn test_api_suggest() {
+    use actix_web::{App, test};
+
+    crate::test_site::site();
+    search_utils::search::reload_index().unwrap();
+    actix_web::rt::System::new().block_on(async {
+        let app = test::init_service(App::new().service(api_suggest)).await;
+        let suggest = async |prefix: &str| {
+            let req = test::TestRequest::get()
+                .uri(&format!("/api/suggest?prefix={prefix}"))
+                .to_request();
+            let suggestions: Vec<serde_json::Value> =
+                test::call_and_read_body_json(&app, req).await;
+            suggestions
+                .into_iter()
+                .map(|s| (s["text"].as_str().unwrap().to_string(), s["kind"].clone()))
+                .collect::<Vec<_>>()
+        };
+        // words as written in the posts, not their stems
+        let texts = suggest("compi").await;
+        assert!(texts.contains(&("compiler".to_string(), "term".into())));
+        assert!(texts.iter().all(|(text, _)| text != "compil"));
+        let texts = suggest("%E6%90%9C%E7%B4%A2%E5%BC%95").await;
+        assert!(texts.contains(&("搜索引擎".to_string(), "term".into())));
+        assert!(suggest("").await.is_empty());
+    });
+}
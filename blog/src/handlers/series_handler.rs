@@ -0,0 +1,115 @@
+use crate::{CONTEXT, errors::RespError, handlers::post_handler::SORT_BY_POSTED_FRONTMATTERS};
+use actix_web::{HttpResponse, route, web};
+use search_utils::{
+    lock::{Lock, Shared},
+    post::FrontMatter,
+};
+use serde::Serialize;
+use std::{
+    collections::HashMap,
+    sync::{Arc, LazyLock},
+};
+use tera::{Context, Tera};
+
+/// Published parts of every series, in reading order
+pub type SeriesMap = HashMap<String, Vec<Arc<FrontMatter>>>;
+
+pub static SERIES: LazyLock<Shared<SeriesMap>> = LazyLock::new(|| Shared::new(init_series()));
+
+pub fn init_series() -> SeriesMap {
+    series_map(SORT_BY_POSTED_FRONTMATTERS.get().iter())
+}
+
+/// Parts go by `series_order`, then by `posted`
+fn series_map<'a>(frontmatters: impl IntoIterator<Item = &'a Arc<FrontMatter>>) -> SeriesMap {
+    let mut map = SeriesMap::new();
+    for fm in frontmatters {
+        if let Some(name) = &fm.series {
+            map.entry(name.clone()).or_default().push(fm.clone());
+        }
+    }
+    for parts in map.values_mut() {
+        parts.sort_by_key(|fm| (fm.series_order.unwrap_or(u32::MAX), fm.posted));
+    }
+    map
+}
+
+/// "part N of M" navigation of a post within its series
+#[derive(Debug, Serialize)]
+struct SeriesNav<'a> {
+    name: &'a str,
+    part: usize,
+    total: usize,
+    prev: Option<&'a FrontMatter>,
+    next: Option<&'a FrontMatter>,
+}
+
+pub fn insert_series_nav(context: &mut Context, frontmatter: &FrontMatter) {
+    let Some(name) = &frontmatter.series else {
+        return;
+    };
+    let map = SERIES.get();
+    let Some(parts) = map.get(name) else {
+        return;
+    };
+    let Some(index) = parts
+        .iter()
+        .position(|fm| fm.file_name == frontmatter.file_name)
+    else {
+        return;
+    };
+    let nav = SeriesNav {
+        name,
+        part: index + 1,
+        total: parts.len(),
+        prev: index.checked_sub(1).map(|i| parts[i].as_ref()),
+        next: parts.get(index + 1).map(AsRef::as_ref),
+    };
+    context.insert("series", &nav);
+}
+
+#[route("/series/{name}", method = "GET", method = "HEAD")]
+pub async fn series(
+    templates: web::Data<Arc<Lock<Tera>>>,
+    name: web::Path<String>,
+) -> Result<HttpResponse, RespError> {
+    let map = SERIES.get();
+    let parts = map.get(name.as_str()).ok_or(RespError::NotFound)?;
+    let mut context = CONTEXT.clone();
+    context.insert("page", "series");
+    context.insert("series_name", name.as_str());
+    context.insert("parts", parts);
+    let html = templates
+        .get()
+        .render("series.html", &context)
+        .inspect_err(|e| log::error!("tera: {e}"))?;
+    Ok(HttpResponse::Ok().content_type("text/html").body(html))
+}
+
+#[test]
+#[allow(clippy::unwrap_used)]
+fn test_series_order() {
+    let fm = |name: &str, posted: &str, extra: &str| {
+        let extra = format!("posted = \"{posted}T00:00:00+08:00\"\n{extra}");
+        Arc::new(search_utils::test_site::frontmatter(name, &extra))
+    };
+    let frontmatters = [
+        fm("b", "2025-02-01", "series = \"s\""),
+        fm("a", "2025-01-01", "series = \"s\"\nseries_order = 2"),
+        fm("c", "2025-03-01", "series = \"s\"\nseries_order = 1"),
+        fm("d", "2025-01-02", "series = \"s\""),
+        fm("other", "2025-01-01", "series = \"t\""),
+        fm("alone", "2025-01-01", ""),
+    ];
+    let map = series_map(&frontmatters);
+    let names = |name: &str| {
+        map[name]
+            .iter()
+            .map(|fm| fm.file_name.as_str())
+            .collect::<Vec<_>>()
+    };
+    // ordered parts first, the others in the order they were posted
+    assert_eq!(names("s"), ["c", "a", "d", "b"]);
+    assert_eq!(names("t"), ["other"]);
+    assert_eq!(map.len(), 2);
+}
@@ -0,0 +1,37 @@
+use crate::{
+    CONTEXT,
+    errors::RespError,
+    handlers::{home_handler::page_count, post_handler::SORT_BY_UPDATED_WITH_RFC2822},
+};
+use actix_web::{HttpResponse, route, web};
+use search_utils::{config::CONFIG, lock::Lock};
+use std::sync::Arc;
+use tera::Tera;
+
+#[route("/sitemap.xml", method = "GET", method = "HEAD")]
+pub async fn sitemap(templates: web::Data<Arc<Lock<Tera>>>) -> Result<HttpResponse, RespError> {
+    let items = SORT_BY_UPDATED_WITH_RFC2822.get();
+    let mut context = CONTEXT.clone();
+    if let Some(first) = items.first() {
+        context.insert("latest_updated", first.updated.as_str());
+    }
+    context.insert("pages", &(1..=page_count()).collect::<Vec<_>>());
+    context.insert("posts", items.as_slice());
+    let xml = templates
+        .get()
+        .render("sitemap.xml", &context)
+        .inspect_err(|e| log::error!("tera: {e}"))?;
+    Ok(HttpResponse::Ok()
+        .content_type("application/xml; charset=utf-8")
+        .body(xml))
+}
+
+#[route("/robots.txt", method = "GET", method = "HEAD")]
+pub async fn robots() -> HttpResponse {
+    HttpResponse::Ok()
+        .content_type("text/plain; charset=utf-8")
+        .body(format!(
+            "User-agent: *\nDisallow: /preview/\n\nSitemap: {}/sitemap.xml\n",
+            CONFIG.site.url
+        ))
+}
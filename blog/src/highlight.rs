@@ -0,0 +1,178 @@
+use crate::errors::CatError;
+use comrak::{
+    adapters::SyntaxHighlighterAdapter,
+    html::{escape, write_opening_tag},
+};
+use search_utils::config::CONFIG;
+use std::{
+    borrow::Cow,
+    collections::HashMap,
+    fmt::{self, Write},
+    sync::LazyLock,
+};
+use syntect::{
+    easy::HighlightLines,
+    highlighting::{Color, Theme, ThemeSet},
+    html::{
+        ClassStyle, IncludeBackground, css_for_theme_with_class_style,
+        line_tokens_to_classed_spans, styled_line_to_highlighted_html,
+    },
+    parsing::{ParseState, ScopeStack, SyntaxReference, SyntaxSet},
+    util::LinesWithEndings,
+};
+
+const CLASS_PREFIX: &str = "hl-";
+const CLASS_STYLE: ClassStyle = ClassStyle::SpacedPrefixed {
+    prefix: CLASS_PREFIX,
+};
+
+pub struct Highlighter {
+    syntaxes: SyntaxSet,
+    light: Theme,
+    /// stylesheet for [`CodeStyle::Classes`], the dark theme applies below `.dark`
+    pub css: String,
+}
+
+pub static HIGHLIGHTER: LazyLock<Highlighter> = LazyLock::new(|| match Highlighter::load() {
+    Ok(h) => h,
+    Err(e) => {
+        log::error!("Can not load the code highlighter!, error: {e}");
+        std::process::exit(1);
+    }
+});
+
+impl Highlighter {
+    fn load() -> Result<Self, CatError> {
+        let config = &CONFIG.highlight;
+        let themes = ThemeSet::load_defaults().themes;
+        let theme = |name: &str| {
+            themes
+                .get(name)
+                .cloned()
+                .ok_or_else(|| CatError::custom(format!("Unknown highlight theme '{name}'")))
+        };
+        let light = theme(&config.light_theme)?;
+        let dark = theme(&config.dark_theme)?;
+        let css_for = |theme: &Theme| {
+            css_for_theme_with_class_style(theme, CLASS_STYLE)
+                .map_err(|e| CatError::internal(e.to_string()))
+        };
+        let css = format!("{}\n.dark {{\n{}}}\n", css_for(&light)?, css_for(&dark)?);
+        Ok(Highlighter {
+            syntaxes: SyntaxSet::load_defaults_newlines(),
+            light,
+            css,
+        })
+    }
+
+    /// Every line closes the spans it opened, since the page splits code blocks by lines
+    fn classed(&self, syntax: &SyntaxReference, code: &str) -> Result<String, syntect::Error> {
+        let mut html = String::with_capacity(code.len() * 2);
+        let mut parse_state = ParseState::new(syntax);
+        let mut stack = ScopeStack::new();
+        for line in LinesWithEndings::from(code) {
+            let ops = parse_state.parse_line(line, &self.syntaxes)?;
+            let text = line.trim_end_matches(['\r', '\n']);
+            let ops = ops
+                .into_iter()
+                .map(|(i, op)| (i.min(text.len()), op))
+                .collect::<Vec<_>>();
+            // reopen the scopes left open by the previous line
+            for scope in stack.as_slice() {
+                let classes = scope
+                    .build_string()
+                    .split('.')
+                    .map(|atom| format!("{CLASS_PREFIX}{atom}"))
+                    .collect::<Vec<_>>()
+                    .join(" ");
+                write!(html, "<span class=\"{classes}\">")?;
+            }
+            let (spans, _) = line_tokens_to_classed_spans(text, &ops, CLASS_STYLE, &mut stack)?;
+            html.push_str(&spans);
+            html.push_str(&"</span>".repeat(stack.len()));
+            html.push_str(&line[text.len()..]);
+        }
+        Ok(html)
+    }
+
+    fn inline(&self, syntax: &SyntaxReference, code: &str) -> Result<String, syntect::Error> {
+        let mut html = String::with_capacity(code.len() * 4);
+        let mut lines = HighlightLines::new(syntax, &self.light);
+        for line in LinesWithEndings::from(code) {
+            let regions = lines.highlight_line(line, &self.syntaxes)?;
+            html.push_str(&styled_line_to_highlighted_html(
+                &regions,
+                IncludeBackground::No,
+            )?);
+        }
+        Ok(html)
+    }
+}
+
+fn css_color(c: Color) -> String {
+    format!("#{:02x}{:02x}{:02x}", c.r, c.g, c.b)
+}
+
+/// How the highlighted code is colored
+#[derive(Debug, Clone, Copy)]
+pub enum CodeStyle {
+    /// `hl-` classes styled by `/highlight.css`, for the pages
+    Classes,
+    /// inline styles of the light theme, for feed readers which don't load our css
+    Inline,
+}
+
+impl SyntaxHighlighterAdapter for CodeStyle {
+    fn write_highlighted(
+        &self,
+        output: &mut dyn fmt::Write,
+        lang: Option<&str>,
+        code: &str,
+    ) -> fmt::Result {
+        let highlighter = &*HIGHLIGHTER;
+        let Some(syntax) = lang
+            .filter(|l| !l.is_empty())
+            .and_then(|l| highlighter.syntaxes.find_syntax_by_token(l))
+        else {
+            return escape(output, code);
+        };
+        let html = match self {
+            CodeStyle::Classes => highlighter.classed(syntax, code),
+            CodeStyle::Inline => highlighter.inline(syntax, code),
+        };
+        match html {
+            Ok(html) => output.write_str(&html),
+            Err(e) => {
+                log::warn!("Can not highlight {} code, error: {e}", syntax.name);
+                escape(output, code)
+            }
+        }
+    }
+
+    fn write_pre_tag(
+        &self,
+        output: &mut dyn fmt::Write,
+        mut attributes: HashMap<&'static str, Cow<'_, str>>,
+    ) -> fmt::Result {
+        if let CodeStyle::Inline = self {
+            let settings = &HIGHLIGHTER.light.settings;
+            let mut style = String::new();
+            if let Some(bg) = settings.background {
+                style.push_str(&format!("background-color:{};", css_color(bg)));
+            }
+            if let Some(fg) = settings.foreground {
+                style.push_str(&format!("color:{};", css_color(fg)));
+            }
+            attributes.insert("style", style.into());
+        }
+        write_opening_tag(output, "pre", attributes)
+    }
+
+    fn write_code_tag(
+        &self,
+        output: &mut dyn fmt::Write,
+        attributes: HashMap<&'static str, Cow<'_, str>>,
+    ) -> fmt::Result {
+        write_opening_tag(output, "code", attributes)
+    }
+}
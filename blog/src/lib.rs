@@ -1,40 +1,89 @@
 #![cfg_attr(not(debug_assertions), deny(clippy::unwrap_used))]
 
-use crate::errors::{CatError, RespError};
+use crate::{
+    errors::{CatError, RespError},
+    highlight::HIGHLIGHTER,
+};
 use actix_files::Files;
 use actix_web::{
     App, HttpResponse, HttpResponseBuilder, HttpServer,
     dev::{Server, ServiceResponse},
-    http::StatusCode,
+    http::{StatusCode, header},
     middleware::{self, Compress, ErrorHandlerResponse, ErrorHandlers},
     web,
 };
+use chrono::{DateTime, Utc};
 use rand::seq::IndexedRandom;
-use search_utils::{blog_path, lock::Lock, post::FRONTMATTER};
+use search_utils::{
+    blog_path,
+    config::CONFIG,
+    lock::Lock,
+    post::{FRONTMATTER, FrontMatter},
+};
 use std::{
     fs, io,
     net::TcpListener,
     path::PathBuf,
-    sync::{Arc, LazyLock},
+    sync::{
+        Arc, LazyLock,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::Duration,
 };
 use tera::Tera;
 
 pub mod errors;
+pub mod export;
 pub mod handlers;
+pub mod highlight;
+pub mod links;
 pub mod notify;
+pub mod page_cache;
+pub mod post_store;
+pub mod render;
 
 #[cfg(debug_assertions)]
 pub mod socket;
+#[cfg(test)]
+mod test_site;
+
+/// Compute the lists of published posts again, from `FRONTMATTER` and the current time
+pub fn reload_published_posts() -> Result<(), CatError> {
+    use crate::handlers::{
+        archive_handler::{ARCHIVES, init_archives},
+        post_handler::{
+            SORT_BY_POSTED_FRONTMATTERS, SORT_BY_UPDATED_FRONTMATTERS,
+            SORT_BY_UPDATED_WITH_RFC2822, initial_feed_items, initial_sort_by_posted_fm,
+            initial_sort_by_updated_fm,
+        },
+        search_handler::{ALL_TAGS, init_tags},
+        series_handler::{SERIES, init_series},
+    };
+
+    *SORT_BY_POSTED_FRONTMATTERS.get_mut() = initial_sort_by_posted_fm();
+    *SORT_BY_UPDATED_FRONTMATTERS.get_mut() = initial_sort_by_updated_fm();
+    *SORT_BY_UPDATED_WITH_RFC2822.get_mut() = initial_feed_items();
+    *ARCHIVES.get_mut() = init_archives()?;
+    *SERIES.get_mut() = init_series();
+    *ALL_TAGS.get_mut() = init_tags();
+    Ok(())
+}
 
 pub fn initialize_static_vars() {
-    use crate::handlers::archive_handler::ARCHIVES;
+    use crate::handlers::{archive_handler::ARCHIVES, series_handler::SERIES};
     use std::sync::LazyLock;
 
     LazyLock::force(&crate::TEMPLATES);
     LazyLock::force(&ARCHIVES);
+    LazyLock::force(&SERIES);
     LazyLock::force(&FRONTMATTER);
+    LazyLock::force(&HIGHLIGHTER);
 }
 
+/// Set by the `export` mode before the statics are initialized,
+/// templates then leave out the features that need the server
+pub static STATIC_EXPORT: AtomicBool = AtomicBool::new(false);
+
 pub static YEAR: LazyLock<i32> = LazyLock::new(|| {
     use chrono::Datelike;
     let now = chrono::Local::now();
@@ -42,7 +91,7 @@ pub static YEAR: LazyLock<i32> = LazyLock::new(|| {
 });
 
 pub static TEMPLATES: LazyLock<Arc<Lock<Tera>>> = LazyLock::new(|| {
-    let mut tera = match Tera::new(blog_path!("/templates/**/*.{html,xml}")) {
+    let mut tera = match Tera::new(&blog_path!("/templates/**/*.{html,xml}")) {
         Ok(t) => t,
         Err(e) => {
             log::error!("Parsing error(s): {}", e);
@@ -56,11 +105,18 @@ pub static TEMPLATES: LazyLock<Arc<Lock<Tera>>> = LazyLock::new(|| {
 
 pub static CONTEXT: LazyLock<tera::Context> = LazyLock::new(|| {
     let mut context = tera::Context::new();
+    let static_export = STATIC_EXPORT.load(Ordering::Relaxed);
     #[cfg(debug_assertions)]
-    {
+    if !static_export {
         context.insert("debug_mode", &true);
+        context.insert("ws_port", &CONFIG.dev.ws_port);
     }
+    context.insert("static_export", &static_export);
     context.insert("YEAR", &*YEAR);
+    context.insert("site_title", &CONFIG.site.title);
+    context.insert("site_url", &CONFIG.site.url);
+    // not the whole config, which holds the paths of the server
+    context.insert("site", &CONFIG.site);
     context
 });
 
@@ -74,11 +130,21 @@ async fn comment_css() -> HttpResponse {
         .body(css)
 }
 
+#[actix_web::get("/highlight.css")]
+async fn highlight_css() -> HttpResponse {
+    HttpResponse::Ok()
+        .insert_header(("Content-Type", "text/css"))
+        .body(HIGHLIGHTER.css.as_str())
+}
+
 async fn not_found_handler() -> Result<HttpResponse, RespError> {
     not_found_page()
 }
 
-fn error_page(title: &str, mut kind: HttpResponseBuilder) -> Result<HttpResponse, RespError> {
+pub(crate) fn error_page(
+    title: &str,
+    mut kind: HttpResponseBuilder,
+) -> Result<HttpResponse, RespError> {
     let mut context = CONTEXT.clone();
     context.insert("page", "not_found");
     context.insert("title", title);
@@ -99,11 +165,22 @@ fn error_page(title: &str, mut kind: HttpResponseBuilder) -> Result<HttpResponse
     Ok(kind.content_type("text/html").body(html))
 }
 
-fn not_found_page() -> Result<HttpResponse, RespError> {
+pub(crate) fn not_found_page() -> Result<HttpResponse, RespError> {
     error_page("Not Found", HttpResponse::NotFound())
 }
 
+/// Errors of the `/api/` routes are already JSON, they are passed through
+fn is_json<B>(res: &ServiceResponse<B>) -> bool {
+    res.response()
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .is_some_and(|content_type| content_type.as_bytes().starts_with(b"application/json"))
+}
+
 fn render_400<B>(res: ServiceResponse<B>) -> Result<ErrorHandlerResponse<B>, actix_web::Error> {
+    if is_json(&res) {
+        return Ok(ErrorHandlerResponse::Response(res.map_into_left_body()));
+    }
     let new_resp = error_page("Bad Request", HttpResponse::BadRequest())
         .map_err(actix_web::error::ErrorBadRequest)?;
     let new_service_resp = res.into_response(new_resp.map_into_right_body());
@@ -112,6 +189,9 @@ fn render_400<B>(res: ServiceResponse<B>) -> Result<ErrorHandlerResponse<B>, act
 }
 
 fn render_500<B>(res: ServiceResponse<B>) -> Result<ErrorHandlerResponse<B>, actix_web::Error> {
+    if is_json(&res) {
+        return Ok(ErrorHandlerResponse::Response(res.map_into_left_body()));
+    }
     let new_resp = error_page("Internal Server Error", HttpResponse::InternalServerError())
         .map_err(actix_web::error::ErrorInternalServerError)?;
     let new_service_resp = res.into_response(new_resp.map_into_right_body());
@@ -120,15 +200,171 @@ fn render_500<B>(res: ServiceResponse<B>) -> Result<ErrorHandlerResponse<B>, act
 }
 
 fn render_404<B>(res: ServiceResponse<B>) -> Result<ErrorHandlerResponse<B>, actix_web::Error> {
+    if is_json(&res) {
+        return Ok(ErrorHandlerResponse::Response(res.map_into_left_body()));
+    }
     let new_resp = not_found_page().map_err(actix_web::error::ErrorBadRequest)?;
     let new_service_resp = res.into_response(new_resp.map_into_right_body());
 
     Ok(ErrorHandlerResponse::Response(new_service_resp))
 }
 
+/// Methods and path of every route registered by [`configure`], for `blog routes`
+pub const ROUTES: &[(&str, &str)] = &[
+    ("GET, HEAD", "/"),
+    ("GET, HEAD", "/pages/{page_num}"),
+    ("GET, HEAD", "/posts/{post_name}"),
+    ("GET, HEAD", "/archives/{post_name}"),
+    ("GET, HEAD", "/preview/{post_name}/{token}"),
+    ("GET, HEAD", "/search"),
+    ("GET, HEAD", "/lucky"),
+    ("GET, HEAD", "/api/search"),
+    ("GET, HEAD", "/api/suggest"),
+    ("GET, HEAD", "/friends"),
+    ("POST", "/api/friend-link"),
+    ("GET, HEAD", "/archives"),
+    ("GET, HEAD", "/series/{name}"),
+    ("GET, HEAD", "/about"),
+    ("GET, HEAD", "/favicon.ico"),
+    ("GET, HEAD", "/index.xml"),
+    ("GET, HEAD", "/atom.xml"),
+    ("GET, HEAD", "/feed.json"),
+    ("GET, HEAD", "/tags/{tag}/index.xml"),
+    ("GET, HEAD", "/tags/{tag}/atom.xml"),
+    ("GET, HEAD", "/tags/{tag}/feed.json"),
+    ("GET, HEAD", "/sitemap.xml"),
+    ("GET, HEAD", "/robots.txt"),
+    ("GET", "/other_data/comment.css"),
+    ("GET", "/highlight.css"),
+];
+
+/// Every route of the blog, shared by the server and the static export
+pub fn configure(cfg: &mut web::ServiceConfig) {
+    cfg.service(handlers::index)
+        .service(handlers::page)
+        .service(handlers::post)
+        .service(handlers::archive_post)
+        .service(handlers::preview)
+        .service(handlers::search)
+        .service(handlers::search_lucky)
+        .service(handlers::api_search)
+        .service(handlers::api_suggest)
+        .service(handlers::friend_links)
+        .service(handlers::post_link)
+        .service(handlers::archive)
+        .service(handlers::series)
+        .service(handlers::about)
+        .service(handlers::favicon)
+        .service(handlers::rss)
+        .service(handlers::atom)
+        .service(handlers::json_feed)
+        .service(handlers::tag_rss)
+        .service(handlers::tag_atom)
+        .service(handlers::tag_json_feed)
+        .service(handlers::sitemap)
+        .service(handlers::robots)
+        .service(comment_css)
+        .service(highlight_css);
+}
+
+/// Bring the search index up to date with the posts, rebuilding it if it's missing
+/// or outdated, so that the server fails at startup instead of at the first search
+pub fn ensure_search_index() -> io::Result<()> {
+    use search_utils::{build_index::build_index, search::reload_index};
+
+    let changes = build_index()
+        .map_err(|e| io::Error::other(format!("Can not build the search index: {e}")))?;
+    if changes.rebuilt {
+        log::warn!("The search index was missing or outdated, it has been rebuilt");
+    } else if !changes.is_empty() {
+        log::info!(
+            "Search index updated, indexed {:?}, removed {:?}",
+            changes.indexed,
+            changes.removed
+        );
+    }
+    reload_index().map_err(|e| io::Error::other(format!("Can not open the search index: {e}")))
+}
+
+/// `kill -HUP` makes the server search a rebuilt index, the pages listing related posts
+/// are rendered again from it
+#[cfg(unix)]
+async fn reload_index_on_hangup() {
+    use actix_web::rt::signal::unix::{SignalKind, signal};
+
+    let mut hangup = match signal(SignalKind::hangup()) {
+        Ok(hangup) => hangup,
+        Err(e) => {
+            log::error!("Can not listen to SIGHUP: {e}");
+            return;
+        }
+    };
+    while hangup.recv().await.is_some() {
+        match search_utils::search::reload_index() {
+            Ok(()) => {
+                post_store::POST_STORE.clear_related();
+                page_cache::PAGE_CACHE.invalidate();
+                log::info!("Search index reloaded");
+            }
+            Err(e) => log::error!("Can not reload the search index: {e}"),
+        }
+    }
+}
+
+/// The earliest time after `now` a post that is not a draft is due
+fn next_publication<'a>(
+    frontmatters: impl IntoIterator<Item = &'a FrontMatter>,
+    now: DateTime<Utc>,
+) -> Option<DateTime<Utc>> {
+    frontmatters
+        .into_iter()
+        .filter(|fm| !fm.draft)
+        .map(|fm| fm.publish_time().to_utc())
+        .filter(|time| *time > now)
+        .min()
+}
+
+/// List and index scheduled posts once they are due, posts may be added while
+/// the debug server runs, so the schedule is looked at again every hour
+async fn publish_scheduled_posts() {
+    use search_utils::{build_index::build_index, search::reload_index};
+    const HOUR: Duration = Duration::from_secs(60 * 60);
+
+    loop {
+        let now = Utc::now();
+        let next = next_publication(FRONTMATTER.get().values().map(AsRef::as_ref), now);
+        let wait = next.map_or(HOUR, |next| {
+            (next - now).to_std().unwrap_or_default().min(HOUR)
+        });
+        actix_web::rt::time::sleep(wait).await;
+        if next.is_none_or(|next| next > Utc::now()) {
+            continue;
+        }
+        if let Err(e) = reload_published_posts() {
+            log::error!("Can not publish the scheduled posts: {e}");
+            continue;
+        }
+        let indexed = actix_web::rt::task::spawn_blocking(|| {
+            build_index()?;
+            reload_index()
+        })
+        .await;
+        match indexed {
+            Ok(Ok(())) => log::info!("Scheduled posts published"),
+            Ok(Err(e)) => log::error!("Can not index the scheduled posts: {e}"),
+            Err(e) => log::error!("Can not index the scheduled posts: {e}"),
+        }
+        post_store::POST_STORE.clear_related();
+        page_cache::PAGE_CACHE.invalidate();
+    }
+}
+
 pub fn start_blog(listener: TcpListener) -> Result<Server, io::Error> {
     #[cfg(debug_assertions)]
     actix_web::rt::spawn(socket::run());
+    #[cfg(unix)]
+    actix_web::rt::spawn(reload_index_on_hangup());
+    actix_web::rt::spawn(publish_scheduled_posts());
     let server = HttpServer::new(move || {
         App::new()
             .app_data(web::Data::new(TEMPLATES.clone()))
@@ -140,24 +376,81 @@ pub fn start_blog(listener: TcpListener) -> Result<Server, io::Error> {
                     .handler(StatusCode::NOT_FOUND, render_404)
                     .handler(StatusCode::INTERNAL_SERVER_ERROR, render_500),
             )
-            .service(Files::new("/static", "blog/static/").use_last_modified(!cfg!(test)))
+            .service(Files::new("/static", blog_path!("/static/")).use_last_modified(!cfg!(test)))
             .wrap(middleware::Logger::default())
             .wrap(Compress::default())
-            .service(handlers::index)
-            .service(handlers::page)
-            .service(handlers::post)
-            .service(handlers::archive_post)
-            .service(handlers::search)
-            .service(handlers::search_lucky)
-            .service(handlers::friend_links)
-            .service(handlers::post_link)
-            .service(handlers::archive)
-            .service(handlers::about)
-            .service(handlers::favicon)
-            .service(handlers::rss)
-            .service(comment_css)
+            .configure(configure)
     })
     .listen(listener)?
     .run();
     Ok(server)
 }
+
+#[test]
+fn test_publish_scheduled_post() {
+    use crate::handlers::post_handler::published_at;
+    use search_utils::test_site::frontmatter;
+
+    let scheduled = frontmatter("scheduled", "publish_at = \"2025-06-01T12:00:00+08:00\"");
+    let publish_at = scheduled.publish_time().to_utc();
+    let draft = frontmatter(
+        "draft",
+        "draft = true\npublish_at = \"2025-05-01T00:00:00+08:00\"",
+    );
+    let frontmatters = [frontmatter("posted", ""), scheduled, draft].map(Arc::new);
+    let before = publish_at - chrono::Duration::seconds(1);
+    let names = |now| {
+        published_at(&frontmatters, now)
+            .iter()
+            .map(|fm| fm.file_name.clone())
+            .collect::<Vec<_>>()
+    };
+
+    // drafts are never due
+    assert_eq!(
+        next_publication(frontmatters.iter().map(AsRef::as_ref), before),
+        Some(publish_at)
+    );
+    assert_eq!(names(before), ["posted"]);
+    assert_eq!(names(publish_at), ["posted", "scheduled"]);
+    assert_eq!(
+        next_publication(frontmatters.iter().map(AsRef::as_ref), publish_at),
+        None,
+        "a published post is not scheduled"
+    );
+}
+
+#[test]
+#[allow(clippy::unwrap_used)]
+fn test_routes() {
+    use actix_web::{http::Method, test};
+
+    test_site::site();
+    actix_web::rt::System::new().block_on(async {
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(TEMPLATES.clone()))
+                .default_service(web::route().to(HttpResponse::ImATeapot))
+                .configure(configure),
+        )
+        .await;
+        for (methods, path) in ROUTES {
+            let uri = path
+                .replace("{page_num}", "1")
+                .replace("{tag}", "rust")
+                .replace(['{', '}'], "");
+            for method in methods.split(", ") {
+                let req = test::TestRequest::default()
+                    .method(Method::from_bytes(method.as_bytes()).unwrap())
+                    .uri(&uri)
+                    .to_request();
+                let status = test::call_service(&app, req).await.status();
+                assert_ne!(
+                    status,
+                    StatusCode::IM_A_TEAPOT,
+                    "{method} {path} is not routed"
+                );
+            }
+        }
+    });
+}
@@ -0,0 +1,235 @@
+use crate::{TEMPLATES, configure};
+use actix_web::{
+    App,
+    http::Uri,
+    test::{TestRequest, call_service, init_service},
+    web,
+};
+use comrak::{Arena, nodes::NodeValue, parse_document};
+use regex::Regex;
+use search_utils::{
+    config::CONFIG,
+    post::{FRONTMATTER, MD_OPTIONS, POST_MD, find_embedded_frontmatter, post_dir, static_file},
+};
+use std::{collections::HashMap, fs, io, sync::LazyLock};
+
+static HTML_LINK_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r#"(?i)<(?:a|img)\b[^>]*?\b(?:href|src)\s*=\s*["']([^"']*)["']"#)
+        .expect("valid regex")
+});
+
+/// A link of a post and the line it's on
+struct Link {
+    url: String,
+    line: usize,
+}
+
+/// Links and images of a post, with lines counted from the top of `post.md`
+fn find_links(md: &str) -> Vec<Link> {
+    let body_start = find_embedded_frontmatter(md).map_or(0, |embedded| embedded.body_start);
+    let (frontmatter, body) = md.split_at(body_start);
+    let line_offset = frontmatter.lines().count();
+    let arena = Arena::new();
+    let root = parse_document(&arena, body, &MD_OPTIONS);
+    let mut links = Vec::new();
+    for node in root.descendants() {
+        let ast = node.data.borrow();
+        let line = line_offset + ast.sourcepos.start.line;
+        match &ast.value {
+            NodeValue::Link(link) | NodeValue::Image(link) => links.push(Link {
+                url: link.url.clone(),
+                line,
+            }),
+            NodeValue::HtmlBlock(block) => {
+                for (i, html) in block.literal.lines().enumerate() {
+                    links.extend(HTML_LINK_RE.captures_iter(html).map(|c| Link {
+                        url: c[1].to_string(),
+                        line: line + i,
+                    }));
+                }
+            }
+            NodeValue::HtmlInline(html) => {
+                links.extend(HTML_LINK_RE.captures_iter(html).map(|c| Link {
+                    url: c[1].to_string(),
+                    line,
+                }));
+            }
+            _ => (),
+        }
+    }
+    links
+}
+
+#[derive(Debug, PartialEq)]
+enum Target {
+    /// a path served by the blog, with its query
+    Internal(String),
+    External,
+    /// anchors in the same page, mail addresses and the like
+    Skipped,
+}
+
+/// Relative links are resolved against `/posts/{post_name}`
+fn classify(url: &str) -> Target {
+    let url = url.split('#').next().unwrap_or_default();
+    if url.is_empty() || url.starts_with("mailto:") || url.starts_with("data:") {
+        Target::Skipped
+    } else if url.contains("://") || url.starts_with("//") {
+        Target::External
+    } else if url.starts_with('/') {
+        Target::Internal(normalize(url))
+    } else {
+        Target::Internal(normalize(&format!("/posts/{url}")))
+    }
+}
+
+/// Resolve `.` and `..` segments
+fn normalize(url: &str) -> String {
+    let (path, query) = match url.split_once('?') {
+        Some((path, query)) => (path, Some(query)),
+        None => (url, None),
+    };
+    let mut segments = Vec::new();
+    for segment in path.split('/').skip(1) {
+        match segment {
+            "." => (),
+            ".." => {
+                segments.pop();
+            }
+            segment => segments.push(segment),
+        }
+    }
+    let mut normalized = format!("/{}", segments.join("/"));
+    if let Some(query) = query {
+        normalized.push('?');
+        normalized.push_str(query);
+    }
+    normalized
+}
+
+/// Check every link of every post, drafts included, internal links must resolve to
+/// a route of [`configure`] or a file under `blog/static`.
+///
+/// Returns the number of broken links, which have been logged.
+pub async fn check_links() -> io::Result<usize> {
+    let app = init_service(
+        App::new()
+            .app_data(web::Data::new(TEMPLATES.clone()))
+            .configure(configure),
+    )
+    .await;
+    let checker = external_checker();
+    // link to whether it's alive
+    let mut checked = HashMap::<String, bool>::new();
+    let mut broken = 0;
+    let mut post_names = FRONTMATTER.get().keys().cloned().collect::<Vec<_>>();
+    post_names.sort();
+    for post_name in post_names {
+        let md = fs::read_to_string(post_dir(&post_name).join(POST_MD))?;
+        for link in find_links(&md) {
+            let alive = match classify(&link.url) {
+                Target::Skipped => continue,
+                Target::External => match (checker, checked.get(&link.url)) {
+                    (None, _) => continue,
+                    (_, Some(alive)) => *alive,
+                    (Some(checker), None) => {
+                        let alive = check_external(checker, &link.url);
+                        checked.insert(link.url.clone(), alive);
+                        alive
+                    }
+                },
+                Target::Internal(path) => match checked.get(&path) {
+                    Some(alive) => *alive,
+                    None => {
+                        let alive = if let Some(file) = static_file(&path) {
+                            file.is_file()
+                        } else if path.parse::<Uri>().is_ok() {
+                            let req = TestRequest::get().uri(&path).to_request();
+                            call_service(&app, req).await.status().is_success()
+                        } else {
+                            false
+                        };
+                        checked.insert(path, alive);
+                        alive
+                    }
+                },
+            };
+            if !alive {
+                log::error!("{post_name}:{}: broken link '{}'", link.line, link.url);
+                broken += 1;
+            }
+        }
+    }
+    log::info!("Checked {} links, {broken} broken", checked.len());
+    Ok(broken)
+}
+
+/// `links.external_checker`, if it's set and this build can use it
+fn external_checker() -> Option<&'static str> {
+    let checker = CONFIG.links.external_checker.as_str();
+    if checker.is_empty() {
+        None
+    } else if cfg!(feature = "external-links") {
+        Some(checker)
+    } else {
+        log::warn!("External links are not checked, the `external-links` feature is disabled");
+        None
+    }
+}
+
+#[cfg(feature = "external-links")]
+fn check_external(checker: &str, url: &str) -> bool {
+    use percent_encoding::{NON_ALPHANUMERIC, utf8_percent_encode};
+
+    let encoded = utf8_percent_encode(url, NON_ALPHANUMERIC).to_string();
+    match ureq::get(checker.replace("{url}", &encoded)).call() {
+        Ok(resp) => resp.status().is_success(),
+        Err(ureq::Error::StatusCode(_)) => false,
+        Err(e) => {
+            log::warn!("Can not check '{url}': {e}");
+            false
+        }
+    }
+}
+
+/// Never called, [`external_checker`] is `None` without the feature
+#[cfg(not(feature = "external-links"))]
+fn check_external(_checker: &str, _url: &str) -> bool {
+    true
+}
+
+#[test]
+fn test_find_links() {
+    let md = "+++\ntitle = \"Links\"\n+++\n\n[next](/posts/next)\n\n\
+              ![cat](/static/img/Pasted%20image.webp)\n\n\
+              <div>\n<img src=\"/static/a.png\">\n</div>\n\n\
+              see <a href=\"https://example.com\">here</a>\n";
+    let links = find_links(md)
+        .into_iter()
+        .map(|link| (link.url, link.line))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        links,
+        [
+            ("/posts/next".to_string(), 5),
+            ("/static/img/Pasted%20image.webp".to_string(), 7),
+            ("/static/a.png".to_string(), 10),
+            ("https://example.com".to_string(), 13),
+        ]
+    );
+}
+
+#[test]
+fn test_classify() {
+    let internal = |path: &str| Target::Internal(path.to_string());
+    assert_eq!(classify("#intro"), Target::Skipped);
+    assert_eq!(classify("mailto:a@b.c"), Target::Skipped);
+    assert_eq!(classify("data:image/png;base64,AA=="), Target::Skipped);
+    assert_eq!(classify("https://example.com/a"), Target::External);
+    assert_eq!(classify("//example.com/a"), Target::External);
+    assert_eq!(classify("/posts/next#part"), internal("/posts/next"));
+    assert_eq!(classify("next?lang=en"), internal("/posts/next?lang=en"));
+    assert_eq!(classify("../static/a.png"), internal("/static/a.png"));
+    assert_eq!(classify("/posts/./a/../b"), internal("/posts/b"));
+    assert_eq!(classify("/../../etc"), internal("/etc"));
+}
@@ -1,24 +1,104 @@
-use blog::{initialize_static_vars, start_blog};
-use std::{io, net::TcpListener};
+use blog::{
+    ROUTES, STATIC_EXPORT, ensure_search_index,
+    export::{check, export},
+    handlers::preview_handler::log_preview_urls,
+    initialize_static_vars,
+    links::check_links,
+    start_blog,
+};
+use clap::{Args, Parser, Subcommand};
+use search_utils::config::CONFIG;
+use std::{io, net::TcpListener, path::PathBuf, sync::atomic::Ordering};
+
+#[derive(Debug, Parser)]
+#[command(version, about = "lhz07's blog")]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+    /// Directory holding site.toml, blog/ and search_utils/
+    #[arg(long, global = true)]
+    root: Option<PathBuf>,
+    /// Log level, overrides RUST_LOG
+    #[arg(long, global = true)]
+    log_level: Option<log::LevelFilter>,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Serve the blog, the default command
+    Serve(ServeArgs),
+    /// Load all content and templates, render every page and exit non-zero on any error
+    Check,
+    /// Check the links and images of every post, exit non-zero on any broken one
+    CheckLinks,
+    /// Print the registered routes
+    Routes,
+    /// Render the site into a directory for a static host
+    Export { dir: PathBuf },
+}
+
+#[derive(Debug, Default, Args)]
+struct ServeArgs {
+    /// Address to listen on, overrides `server.bind` of site.toml
+    #[arg(long)]
+    bind: Option<String>,
+}
 
 #[actix_web::main]
 async fn main() -> io::Result<()> {
-    if std::env::var("RUST_LOG").is_err() {
-        unsafe {
-            #[cfg(debug_assertions)]
-            std::env::set_var("RUST_LOG", "info");
-            #[cfg(not(debug_assertions))]
-            std::env::set_var("RUST_LOG", "warn");
+    let cli = Cli::parse();
+    let default_level = if cfg!(debug_assertions) {
+        "info"
+    } else {
+        "warn"
+    };
+    let mut logger =
+        env_logger::Builder::from_env(env_logger::Env::default().default_filter_or(default_level));
+    if let Some(level) = cli.log_level {
+        logger.filter_level(level);
+    }
+    logger.init();
+    if let Some(root) = &cli.root {
+        std::env::set_current_dir(root)?;
+    }
+    match cli.command.unwrap_or(Command::Serve(ServeArgs::default())) {
+        Command::Serve(args) => serve(args).await,
+        Command::Check => {
+            initialize_static_vars();
+            let failures = check().await?;
+            if failures > 0 {
+                return Err(io::Error::other(format!("{failures} checks failed")));
+            }
+            Ok(())
+        }
+        Command::CheckLinks => {
+            initialize_static_vars();
+            let broken = check_links().await?;
+            if broken > 0 {
+                return Err(io::Error::other(format!("{broken} broken links")));
+            }
+            Ok(())
+        }
+        Command::Routes => {
+            for (methods, path) in ROUTES {
+                println!("{methods:<10} {path}");
+            }
+            println!("{:<10} /static/{{file}}", "GET, HEAD");
+            Ok(())
+        }
+        Command::Export { dir } => {
+            STATIC_EXPORT.store(true, Ordering::Relaxed);
+            initialize_static_vars();
+            export(&dir).await
         }
     }
-    env_logger::init();
+}
+
+async fn serve(args: ServeArgs) -> io::Result<()> {
     initialize_static_vars();
-    let path = if cfg!(debug_assertions) {
-        "0.0.0.0:8000"
-    } else {
-        "127.0.0.1:8000"
-    };
-    let listener = TcpListener::bind(path)?;
+    ensure_search_index()?;
+    log_preview_urls();
+    let listener = TcpListener::bind(args.bind.as_ref().unwrap_or(&CONFIG.server.bind))?;
     #[cfg(debug_assertions)]
     {
         use tokio::net::UdpSocket;
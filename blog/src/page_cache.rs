@@ -0,0 +1,223 @@
+use crate::errors::RespError;
+use actix_web::{
+    HttpMessage, HttpRequest, HttpResponse,
+    http::header::{ETag, EntityTag, HttpDate, IfModifiedSince, IfNoneMatch, LastModified},
+    web::Bytes,
+};
+use base64::{Engine, prelude::BASE64_URL_SAFE_NO_PAD};
+use sha1::Digest;
+use std::{
+    collections::HashMap,
+    sync::{
+        Arc, LazyLock, PoisonError, RwLock,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::{Duration, SystemTime},
+};
+
+/// A rendered page or feed, with its validators for conditional GET
+#[derive(Debug)]
+struct CachedPage {
+    body: Bytes,
+    content_type: &'static str,
+    etag: EntityTag,
+    last_modified: HttpDate,
+    /// `last_modified` as given to [`PageCache::respond`], not truncated to seconds
+    sources_modified: SystemTime,
+    version: u64,
+}
+
+impl CachedPage {
+    fn new(
+        body: String,
+        content_type: &'static str,
+        last_modified: SystemTime,
+        version: u64,
+    ) -> Self {
+        let hash = sha1::Sha1::digest(body.as_bytes());
+        // weak, the Compress middleware may encode the body differently
+        let etag = EntityTag::new_weak(BASE64_URL_SAFE_NO_PAD.encode(hash));
+        // HTTP dates have no fraction of a second
+        let secs = last_modified
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        CachedPage {
+            body: body.into(),
+            content_type,
+            etag,
+            last_modified: (SystemTime::UNIX_EPOCH + Duration::from_secs(secs)).into(),
+            sources_modified: last_modified,
+            version,
+        }
+    }
+
+    /// `If-None-Match` takes precedence over `If-Modified-Since`
+    fn not_modified(&self, req: &HttpRequest) -> bool {
+        match req.get_header::<IfNoneMatch>() {
+            Some(IfNoneMatch::Any) => true,
+            Some(IfNoneMatch::Items(tags)) => tags.iter().any(|tag| tag.weak_eq(&self.etag)),
+            None => req
+                .get_header::<IfModifiedSince>()
+                .is_some_and(|IfModifiedSince(since)| self.last_modified <= since),
+        }
+    }
+
+    fn respond(&self, req: &HttpRequest) -> HttpResponse {
+        let not_modified = self.not_modified(req);
+        let mut resp = if not_modified {
+            HttpResponse::NotModified()
+        } else {
+            HttpResponse::Ok()
+        };
+        resp.insert_header(ETag(self.etag.clone()))
+            .insert_header(LastModified(self.last_modified));
+        if not_modified {
+            resp.finish()
+        } else {
+            resp.content_type(self.content_type).body(self.body.clone())
+        }
+    }
+}
+
+/// Rendered responses keyed by request path, so that a page is rendered once per content version.
+///
+/// An entry is rendered again once the sources of the page are modified,
+/// every entry is dropped when the posts or templates are reloaded.
+#[derive(Debug, Default)]
+pub struct PageCache {
+    pages: RwLock<HashMap<String, Arc<CachedPage>>>,
+    version: AtomicU64,
+}
+
+pub static PAGE_CACHE: LazyLock<PageCache> = LazyLock::new(PageCache::default);
+
+impl PageCache {
+    /// Answer `req` from the cache, calling `render` on a miss.
+    /// `last_modified` is the newest change of the posts on the page,
+    /// a page cached with another one is stale.
+    pub fn respond(
+        &self,
+        req: &HttpRequest,
+        content_type: &'static str,
+        last_modified: SystemTime,
+        render: impl FnOnce() -> Result<String, RespError>,
+    ) -> Result<HttpResponse, RespError> {
+        let version = self.version.load(Ordering::Acquire);
+        if let Some(page) = self
+            .pages
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .get(req.path())
+            && page.version == version
+            && page.sources_modified == last_modified
+        {
+            return Ok(page.respond(req));
+        }
+        let page = Arc::new(CachedPage::new(
+            render()?,
+            content_type,
+            last_modified,
+            version,
+        ));
+        {
+            let mut pages = self.pages.write().unwrap_or_else(PoisonError::into_inner);
+            // skip pages rendered from the content before a reload
+            if self.version.load(Ordering::Acquire) == version {
+                pages.insert(req.path().to_string(), page.clone());
+            }
+        }
+        Ok(page.respond(req))
+    }
+
+    pub fn invalidate(&self) {
+        let mut pages = self.pages.write().unwrap_or_else(PoisonError::into_inner);
+        self.version.fetch_add(1, Ordering::AcqRel);
+        pages.clear();
+    }
+}
+
+#[test]
+#[allow(clippy::unwrap_used)]
+fn test_conditional_get() {
+    use actix_web::{http::StatusCode, test::TestRequest};
+
+    let cache = PageCache::default();
+    let modified = SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+    let req = TestRequest::get().uri("/posts/a").to_http_request();
+    let resp = cache
+        .respond(&req, "text/html", modified, || Ok("a".to_string()))
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    let etag = resp.headers().get("etag").unwrap().clone();
+    let last_modified = resp.headers().get("last-modified").unwrap().clone();
+    let unreachable = || -> Result<String, RespError> { panic!("cached page rendered again") };
+
+    let req = TestRequest::get()
+        .uri("/posts/a")
+        .insert_header(("if-none-match", etag.clone()))
+        .to_http_request();
+    let resp = cache
+        .respond(&req, "text/html", modified, unreachable)
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::NOT_MODIFIED);
+
+    let req = TestRequest::get()
+        .uri("/posts/a")
+        .insert_header(("if-modified-since", last_modified))
+        .to_http_request();
+    let resp = cache
+        .respond(&req, "text/html", modified, unreachable)
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::NOT_MODIFIED);
+
+    // another etag wins over a matching date
+    let req = TestRequest::get()
+        .uri("/posts/a")
+        .insert_header(("if-none-match", "W/\"other\""))
+        .insert_header(("if-modified-since", "Sun, 01 Jan 2090 00:00:00 GMT"))
+        .to_http_request();
+    let resp = cache
+        .respond(&req, "text/html", modified, unreachable)
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+}
+
+#[test]
+#[allow(clippy::unwrap_used)]
+fn test_stale_pages() {
+    use actix_web::{body::to_bytes, http::StatusCode, test::TestRequest};
+    use std::cell::Cell;
+
+    let cache = PageCache::default();
+    let renders = Cell::new(0);
+    let render = |body: &'static str| {
+        renders.set(renders.get() + 1);
+        Ok(body.to_string())
+    };
+    let modified = SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+    let req = TestRequest::get().uri("/posts/a").to_http_request();
+    cache
+        .respond(&req, "text/html", modified, || render("old"))
+        .unwrap();
+    cache
+        .respond(&req, "text/html", modified, || render("old"))
+        .unwrap();
+    assert_eq!(renders.get(), 1);
+
+    // the post was edited
+    let edited = modified + Duration::from_millis(10);
+    let resp = cache
+        .respond(&req, "text/html", edited, || render("new"))
+        .unwrap();
+    assert_eq!(renders.get(), 2);
+    assert_eq!(resp.status(), StatusCode::OK);
+    let body = actix_web::rt::System::new().block_on(to_bytes(resp.into_body()));
+    assert_eq!(body.unwrap(), "new");
+
+    cache.invalidate();
+    cache
+        .respond(&req, "text/html", edited, || render("new"))
+        .unwrap();
+    assert_eq!(renders.get(), 3);
+}
@@ -0,0 +1,151 @@
+use crate::{
+    highlight::CodeStyle,
+    render::{TocEntry, render_post},
+};
+use search_utils::{
+    errors::SearchError,
+    post::{FrontMatter, POST_FRONTMATTER, POST_MD, extract_md, post_dir},
+    search::related_posts,
+};
+use std::{
+    collections::HashMap,
+    fs, io,
+    sync::{Arc, LazyLock, OnceLock, PoisonError, RwLock},
+    time::SystemTime,
+};
+
+/// Modification times of the files a post is rendered from
+#[derive(Debug, PartialEq, Eq)]
+struct PostVersion {
+    md: SystemTime,
+    /// `None` when the frontmatter is embedded in `post.md`
+    frontmatter: Option<SystemTime>,
+}
+
+impl PostVersion {
+    fn newest(&self) -> SystemTime {
+        self.frontmatter
+            .map_or(self.md, |frontmatter| frontmatter.max(self.md))
+    }
+
+    fn read(post_name: &str) -> io::Result<Self> {
+        let dir = post_dir(post_name);
+        let md = fs::metadata(dir.join(POST_MD))?.modified()?;
+        let frontmatter = fs::metadata(dir.join(POST_FRONTMATTER))
+            .and_then(|meta| meta.modified())
+            .ok();
+        Ok(PostVersion { md, frontmatter })
+    }
+}
+
+#[derive(Debug)]
+pub struct RenderedPost {
+    pub html: String,
+    pub toc: Vec<TocEntry>,
+    /// rendered for the feeds on first use
+    md: String,
+    feed_html: OnceLock<String>,
+    version: PostVersion,
+}
+
+impl RenderedPost {
+    /// Code blocks use inline styles, for feed readers
+    pub fn feed_html(&self) -> &str {
+        self.feed_html
+            .get_or_init(|| render_post(&self.md, CodeStyle::Inline).html)
+    }
+}
+
+const RELATED_POSTS: usize = 3;
+
+/// Rendered posts keyed by `file_name`, so that comrak only runs once per post.
+///
+/// An entry is rendered again once its `post.md` or frontmatter is modified.
+#[derive(Debug, Default)]
+pub struct PostStore {
+    posts: RwLock<HashMap<String, Arc<RenderedPost>>>,
+    /// file names of the most similar posts, searched for by [`PostStore::related`]
+    related: RwLock<HashMap<String, Arc<[String]>>>,
+}
+
+pub static POST_STORE: LazyLock<PostStore> = LazyLock::new(PostStore::default);
+
+/// The newest of `updated` and the modification of the files a post is rendered from,
+/// pages showing the content of the post are stale once it changes
+pub fn post_modified(fm: &FrontMatter) -> SystemTime {
+    let updated = (*fm.updated).into();
+    PostVersion::read(&fm.file_name).map_or(updated, |version| version.newest().max(updated))
+}
+
+impl PostStore {
+    pub fn get(&self, post_name: &str) -> Result<Arc<RenderedPost>, SearchError> {
+        let version = PostVersion::read(post_name)?;
+        if let Some(post) = self
+            .posts
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .get(post_name)
+            && post.version == version
+        {
+            return Ok(post.clone());
+        }
+        let md_text = extract_md(post_name)?;
+        let rendered = render_post(&md_text, CodeStyle::Classes);
+        let post = Arc::new(RenderedPost {
+            html: rendered.html,
+            toc: rendered.toc,
+            md: md_text,
+            feed_html: OnceLock::new(),
+            version,
+        });
+        self.posts
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+            .insert(post_name.to_string(), post.clone());
+        Ok(post)
+    }
+
+    /// The most similar posts, a failed search is not kept and runs again next time
+    pub fn related(&self, fm: &FrontMatter) -> Arc<[String]> {
+        if let Some(related) = self
+            .related
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .get(&fm.file_name)
+        {
+            return related.clone();
+        }
+        match related_posts(fm, RELATED_POSTS) {
+            Ok(related) => {
+                let related = Arc::<[String]>::from(related);
+                self.related
+                    .write()
+                    .unwrap_or_else(PoisonError::into_inner)
+                    .insert(fm.file_name.clone(), related.clone());
+                related
+            }
+            Err(e) => {
+                log::error!("Can not find related posts of '{}': {e}", fm.file_name);
+                Arc::default()
+            }
+        }
+    }
+
+    /// Forget the related posts, they are searched again in a reloaded index
+    pub fn clear_related(&self) {
+        self.related
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clear();
+    }
+
+    /// Drop the posts whose source files have changed or disappeared, and the related
+    /// posts of every post, as the index has been updated with them.
+    pub fn refresh(&self) {
+        self.posts
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+            .retain(|name, post| PostVersion::read(name).is_ok_and(|v| v == post.version));
+        self.clear_related();
+    }
+}
@@ -0,0 +1,266 @@
+use crate::highlight::CodeStyle;
+use comrak::{
+    Anchorizer, Arena,
+    adapters::{HeadingAdapter, HeadingMeta},
+    format_html_with_plugins,
+    html::{collect_text, escape, escape_href},
+    nodes::{AstNode, NodeValue, Sourcepos},
+    options::Plugins,
+    parse_document,
+};
+use regex::Captures;
+use search_utils::post::{IMG_RE, MD_OPTIONS, SRC_RE, static_file};
+use serde::Serialize;
+use std::{
+    fmt,
+    iter::Peekable,
+    sync::atomic::{AtomicUsize, Ordering},
+    vec,
+};
+
+/// A heading in the table of contents, with the headings nested below it
+#[derive(Debug, Serialize)]
+pub struct TocEntry {
+    pub level: u8,
+    pub id: String,
+    pub text: String,
+    pub children: Vec<TocEntry>,
+}
+
+#[derive(Debug)]
+pub struct Rendered {
+    pub html: String,
+    pub toc: Vec<TocEntry>,
+}
+
+struct Heading {
+    level: u8,
+    id: String,
+    text: String,
+}
+
+/// Gives every heading the id collected from the AST, in document order,
+/// and appends a permalink anchor to it.
+struct HeadingIds {
+    ids: Vec<String>,
+    next: AtomicUsize,
+}
+
+impl HeadingIds {
+    fn current(&self) -> &str {
+        let index = self.next.load(Ordering::Relaxed).saturating_sub(1);
+        self.ids.get(index).map(String::as_str).unwrap_or_default()
+    }
+}
+
+impl HeadingAdapter for HeadingIds {
+    fn enter(
+        &self,
+        output: &mut dyn fmt::Write,
+        heading: &HeadingMeta,
+        _sourcepos: Option<Sourcepos>,
+    ) -> fmt::Result {
+        self.next.fetch_add(1, Ordering::Relaxed);
+        write!(output, "<h{} id=\"{}\">", heading.level, self.current())
+    }
+
+    fn exit(&self, output: &mut dyn fmt::Write, heading: &HeadingMeta) -> fmt::Result {
+        writeln!(
+            output,
+            "<a class=\"heading-anchor\" href=\"#{}\" aria-label=\"Permalink\">#</a></h{}>",
+            self.current(),
+            heading.level
+        )
+    }
+}
+
+/// Make a stable id for a heading, keeping CJK characters as they are
+fn heading_id(anchorizer: &mut Anchorizer, text: &str) -> String {
+    let id = anchorizer.anchorize(text);
+    // headings made only of symbols have nothing left to anchorize
+    if id.is_empty() || id.starts_with('-') {
+        anchorizer.anchorize(&format!("section{id}"))
+    } else {
+        id
+    }
+}
+
+fn nest_toc(headings: &mut Peekable<vec::IntoIter<Heading>>, parent_level: u8) -> Vec<TocEntry> {
+    let mut entries = Vec::new();
+    while let Some(heading) = headings.next_if(|h| h.level > parent_level) {
+        let children = nest_toc(headings, heading.level);
+        entries.push(TocEntry {
+            level: heading.level,
+            id: heading.id,
+            text: heading.text,
+            children,
+        });
+    }
+    entries
+}
+
+/// `/static/a.png` to `/static/a.webp`, if that file exists
+fn webp_sibling(url: &str) -> Option<String> {
+    let (stem, ext) = url.strip_prefix("/static/")?.rsplit_once('.')?;
+    if !matches!(ext.to_ascii_lowercase().as_str(), "png" | "jpg" | "jpeg") {
+        return None;
+    }
+    let webp = format!("/static/{stem}.webp");
+    static_file(&webp)?.is_file().then_some(webp)
+}
+
+fn picture(webp: &str, img: &str) -> String {
+    let mut html = String::from("<picture><source srcset=\"");
+    escape_href(&mut html, webp, false).expect("Writing to String cannot fail");
+    html.push_str("\" type=\"image/webp\" />");
+    html.push_str(img);
+    html.push_str("</picture>");
+    html
+}
+
+/// Wrap local png and jpg images with a `.webp` sibling into `<picture>`,
+/// the original stays as the fallback
+fn add_webp_sources<'a>(root: &'a AstNode<'a>) {
+    let nodes = root.descendants().collect::<Vec<_>>();
+    for node in nodes {
+        if let Some(picture) = image_picture(node) {
+            node.data_mut().value = NodeValue::HtmlInline(picture);
+            while let Some(child) = node.first_child() {
+                child.detach();
+            }
+            continue;
+        }
+        match &mut node.data_mut().value {
+            NodeValue::HtmlBlock(block) => rewrite_html(&mut block.literal),
+            NodeValue::HtmlInline(html) => rewrite_html(html),
+            _ => (),
+        }
+    }
+}
+
+/// The `<picture>` replacing a markdown image, if it has a `.webp` sibling
+fn image_picture<'a>(node: &'a AstNode<'a>) -> Option<String> {
+    let ast = node.data();
+    let NodeValue::Image(link) = &ast.value else {
+        return None;
+    };
+    let webp = webp_sibling(&link.url)?;
+    // the same markup comrak renders for an image
+    let mut img = String::from("<img src=\"");
+    escape_href(&mut img, &link.url, false).expect("Writing to String cannot fail");
+    img.push_str("\" alt=\"");
+    escape(&mut img, &collect_text(node)).expect("Writing to String cannot fail");
+    if !link.title.is_empty() {
+        img.push_str("\" title=\"");
+        escape(&mut img, &link.title).expect("Writing to String cannot fail");
+    }
+    img.push_str("\" />");
+    Some(picture(&webp, &img))
+}
+
+/// `<img>` tags written as raw html
+fn rewrite_html(html: &mut String) {
+    if html.contains("<picture") || !IMG_RE.is_match(html) {
+        return;
+    }
+    let rewritten = IMG_RE.replace_all(html, |c: &Captures| {
+        let img = &c[0];
+        let webp = SRC_RE.captures(img).and_then(|src| webp_sibling(&src[1]));
+        match webp {
+            Some(webp) => picture(&webp, img),
+            None => img.to_string(),
+        }
+    });
+    *html = rewritten.into_owned();
+}
+
+pub fn render_post(md: &str, code_style: CodeStyle) -> Rendered {
+    let arena = Arena::new();
+    let root = parse_document(&arena, md, &MD_OPTIONS);
+    add_webp_sources(root);
+
+    let mut anchorizer = Anchorizer::new();
+    let mut headings = Vec::new();
+    for node in root.descendants() {
+        if let NodeValue::Heading(ref heading) = node.data().value {
+            let text = collect_text(node);
+            let id = heading_id(&mut anchorizer, &text);
+            headings.push(Heading {
+                level: heading.level,
+                id,
+                text: text.trim().to_string(),
+            });
+        }
+    }
+
+    let heading_ids = HeadingIds {
+        ids: headings.iter().map(|h| h.id.clone()).collect(),
+        next: AtomicUsize::new(0),
+    };
+    let mut plugins = Plugins::default();
+    plugins.render.heading_adapter = Some(&heading_ids);
+    plugins.render.codefence_syntax_highlighter = Some(&code_style);
+    let mut html = String::with_capacity(md.len() * 2);
+    format_html_with_plugins(root, &MD_OPTIONS, &mut html, &plugins)
+        .expect("Writing to String cannot fail");
+
+    let toc = nest_toc(&mut headings.into_iter().peekable(), 0);
+    Rendered { html, toc }
+}
+
+#[test]
+fn test_heading_ids() {
+    crate::test_site::site();
+    let md = "## 核心思路\n\n### Step 1: 分词\n\n### Step 1: 分词\n\n## ???\n\n#### deep\n";
+    let rendered = render_post(md, CodeStyle::Classes);
+    assert!(rendered.html.contains("<h2 id=\"核心思路\">"));
+    assert!(rendered.html.contains("<h3 id=\"step-1-分词-1\">"));
+    assert!(rendered.html.contains("href=\"#section\""));
+    let ids = |entries: &[TocEntry]| entries.iter().map(|e| e.id.clone()).collect::<Vec<_>>();
+    assert_eq!(ids(&rendered.toc), ["核心思路", "section"]);
+    assert_eq!(
+        ids(&rendered.toc[0].children),
+        ["step-1-分词", "step-1-分词-1"]
+    );
+    assert_eq!(ids(&rendered.toc[1].children), ["deep"]);
+}
+
+#[test]
+#[allow(clippy::unwrap_used)]
+fn test_webp_sources() {
+    crate::test_site::site();
+    // a markdown image with a `.webp` sibling
+    let html = render_post(
+        "![Cloverta](/static/img/friends/cloverta.png \"avatar\")",
+        CodeStyle::Classes,
+    )
+    .html;
+    assert!(html.contains(
+        "<picture><source srcset=\"/static/img/friends/cloverta.webp\" type=\"image/webp\" />\
+         <img src=\"/static/img/friends/cloverta.png\" alt=\"Cloverta\" title=\"avatar\" /></picture>"
+    ));
+
+    // a raw `<img>`
+    let html = render_post(
+        "<img src=\"/static/img/friends/next_cat.jpg\" width=\"100\">",
+        CodeStyle::Classes,
+    )
+    .html;
+    assert!(html.contains(
+        "<picture><source srcset=\"/static/img/friends/next_cat.webp\" type=\"image/webp\" />\
+         <img src=\"/static/img/friends/next_cat.jpg\" width=\"100\"></picture>"
+    ));
+
+    // without a sibling, or outside of `/static`, images are left alone
+    for md in [
+        "![avatar](/static/img/avatar_small.png)",
+        "<img src=\"/static/img/avatar_small.png\">",
+        "![remote](https://example.com/a.png)",
+    ] {
+        assert!(
+            !render_post(md, CodeStyle::Classes)
+                .html
+                .contains("<picture")
+        );
+    }
+}
@@ -1,18 +1,13 @@
-use crate::{
-    TEMPLATES,
-    handlers::{
-        archive_handler::{ARCHIVES, init_archives},
-        post_handler::{
-            SORT_BY_POSTED_FRONTMATTERS, SORT_BY_UPDATED_FRONTMATTERS, initial_sort_by_posted_fm,
-            initial_sort_by_updated_fm,
-        },
-    },
-};
+use crate::{TEMPLATES, page_cache::PAGE_CACHE, post_store::POST_STORE, reload_published_posts};
 use actix_web::rt::net::TcpStream;
 use auto_builder::{bitcode, socket::SocketMsg};
 use search_utils::{
+    build_index::index_post,
+    config::CONFIG,
     formatter::{self, ShorterPath},
-    post::{FRONTMATTER, initial_fm},
+    manifest::bump_updated,
+    post::{FRONTMATTER, POST_FRONTMATTER, POST_MD, initial_fm},
+    search::reload_index,
 };
 use std::{
     io,
@@ -28,7 +23,7 @@ pub async fn run() {
 }
 
 async fn connect() -> Result<(), io::Error> {
-    let mut stream = TcpStream::connect("127.0.0.1:9002").await?;
+    let mut stream = TcpStream::connect(("127.0.0.1", CONFIG.dev.socket_port)).await?;
     log::info!("Connected to the server: {:?}", stream.peer_addr()?);
     let mut last_format = time::Instant::now();
     loop {
@@ -60,8 +55,40 @@ async fn connect() -> Result<(), io::Error> {
     }
 }
 
+/// Names of the posts whose `post.md` or frontmatter is among the changed paths,
+/// removed posts included
+fn post_names(paths: &[String]) -> Vec<&str> {
+    let mut names = paths
+        .iter()
+        .map(Path::new)
+        .filter(|path| path.ends_with(POST_MD) || path.ends_with(POST_FRONTMATTER))
+        .filter_map(|path| path.parent()?.file_name()?.to_str())
+        .collect::<Vec<_>>();
+    names.sort_unstable();
+    names.dedup();
+    names
+}
+
 fn reload(paths: Vec<String>, last_format: &mut Instant) -> Result<(), ()> {
     let mut ins = time::Instant::now();
+    // before the frontmatters are reloaded, a formatter rewrite is not a change,
+    // removed posts are skipped there and dropped from the index below
+    let names = post_names(&paths);
+    match bump_updated(names.iter().copied()) {
+        Ok(bumped) => bumped
+            .iter()
+            .for_each(|name| log::info!("Bumped `updated` of '{name}'")),
+        Err(e) => log::error!("Can not bump `updated`: {e}"),
+    }
+    // before the related posts are rendered again
+    for name in &names {
+        if let Err(e) = index_post(name) {
+            log::error!("Can not index '{name}': {e}");
+        }
+    }
+    if let Err(e) = reload_index() {
+        log::error!("Can not reload the search index: {e}");
+    }
     // reload tera templates
     TEMPLATES.get_mut().full_reload().map_err(|e| {
         log::error!("tera error: {e}");
@@ -72,17 +99,20 @@ fn reload(paths: Vec<String>, last_format: &mut Instant) -> Result<(), ()> {
     })?;
     *FRONTMATTER.get_mut() = map;
     // reload sorted fms
-    *SORT_BY_POSTED_FRONTMATTERS.get_mut() = initial_sort_by_posted_fm();
-    *SORT_BY_UPDATED_FRONTMATTERS.get_mut() = initial_sort_by_updated_fm();
-    let map = init_archives().map_err(|e| {
+    reload_published_posts().map_err(|e| {
         log::error!("archives error: {e}");
     })?;
-    *ARCHIVES.get_mut() = map;
+    // drop the rendered posts whose sources changed
+    POST_STORE.refresh();
+    PAGE_CACHE.invalidate();
     log::info!("tera cost: {:?}", ins.elapsed());
     ins = time::Instant::now();
     log::info!("Templates reloaded.");
     if last_format.elapsed() > Duration::from_secs(3) {
-        for path in paths {
+        for path in paths
+            .iter()
+            .filter(|path| path.ends_with(POST_MD) && Path::new(path).is_file())
+        {
             if let Err(e) = formatter::format_md_file(path.as_ref()) {
                 let path = Path::new(&path);
                 log::error!("format md file {}: {e}", path.shorter_path().display())
@@ -93,3 +123,17 @@ fn reload(paths: Vec<String>, last_format: &mut Instant) -> Result<(), ()> {
     *last_format = time::Instant::now();
     Ok(())
 }
+
+#[test]
+fn test_post_names() {
+    let paths = [
+        "/blog/posts/a/post.md",
+        "/blog/posts/a/post_frontmatter.toml",
+        "/blog/posts/removed/post.md",
+        "/blog/posts/b/post_frontmatter.toml",
+        "/blog/templates/post.html",
+        "/blog/static/css/index.css",
+    ]
+    .map(String::from);
+    assert_eq!(post_names(&paths), ["a", "b", "removed"]);
+}
@@ -0,0 +1,33 @@
+//! `CONFIG` of the tests, the posts of the blog with a search index in a temporary folder
+#![allow(clippy::unwrap_used)]
+
+use std::{env, fs, path::Path, sync::LazyLock};
+
+static SITE: LazyLock<()> = LazyLock::new(|| {
+    let manifest_dir = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let root = env::temp_dir().join(format!("blog-test-{}", std::process::id()));
+    if root.exists() {
+        fs::remove_dir_all(&root).unwrap();
+    }
+    let vars = [
+        ("BLOG_CONFIG", manifest_dir.join("../site.toml")),
+        ("BLOG_PATHS_BLOG", manifest_dir.to_path_buf()),
+        ("BLOG_PATHS_SEARCH", manifest_dir.join("../search_utils")),
+        ("BLOG_PATHS_INDEX", root.join("index")),
+        (
+            "BLOG_PATHS_CONTENT_HASHES",
+            root.join("content_hashes.toml"),
+        ),
+        ("BLOG_PATHS_FRIEND_REQUESTS", root.join("friend_requests")),
+    ];
+    for (key, value) in vars {
+        // SAFETY: every test calls `site` before anything reads the environment
+        unsafe { env::set_var(key, value) };
+    }
+    search_utils::build_index::build_index().unwrap();
+});
+
+/// Point `CONFIG` to the blog and build its index, call it first in every test
+pub fn site() {
+    LazyLock::force(&SITE);
+}
@@ -162,9 +162,7 @@ document.querySelectorAll("pre").forEach((pre) => {
   pre.parentNode.insertBefore(wrapper, pre);
   wrapper.appendChild(pre);
   const code = pre.querySelector("code");
-  // highlight code
-  hljs.highlightElement(code);
-  // convert to divs
+  // convert to divs, the server closes the highlighted spans at the end of every line
   const html = code.innerHTML.trimEnd();
   const lines = html.split(/\r?\n/);
   code.innerHTML = lines.map((line) => `<div>${line || " "}</div>`).join("");
@@ -246,71 +244,21 @@ document.querySelectorAll(".markdown-body a[href]").forEach((a) => {
 });
 
 const tocToggleButton = document.getElementById("toc-toggle-btn");
-// Generate TOC starting from <h2>, with smooth scrolling and active highlighting
+// The TOC and the heading ids are rendered by the server, add smooth scrolling and active highlighting
 function generateTOC(containerSelector = "#toc") {
+  const tocContainer = document.querySelector(containerSelector);
+  if (!tocContainer) return;
   const article_header = document.getElementById("article-header");
   const article = document.getElementById("article-content");
   const main_offset =
     article_header.offsetHeight + article.offsetTop + article.offsetHeight - 65;
-  // Collect all headings
-  const allHeadings = Array.from(
-    document.querySelectorAll("h2, h3, h4, h5, h6"),
-  );
-  if (allHeadings.length === 0) return;
-
-  // Find the smallest heading level (h1=1, h2=2, etc.)
-  const minLevel = Math.min(...allHeadings.map((h) => parseInt(h.tagName[1])));
-  const headings = allHeadings.filter(
-    (h) => parseInt(h.tagName[1]) >= minLevel,
-  );
-
-  const tocContainer = document.querySelector(containerSelector);
-  const tocRoot = document.createElement("ul");
-
-  // Create counters for up to 6 levels
-  const counters = [0, 0, 0, 0, 0];
-  let currentListStack = [tocRoot];
-  let lastLevel = 1;
-  for (const heading of headings) {
-    // Normalize heading level relative to the top-level heading
-    const level = parseInt(heading.tagName[1]) - minLevel + 1;
-    counters[level - 1]++;
-    for (let i = level; i < counters.length; i++) counters[i] = 0;
+  const headings = Array.from(tocContainer.querySelectorAll("a"))
+    .map((a) => document.getElementById(a.getAttribute("href").slice(1)))
+    .filter((h) => h !== null);
+  if (headings.length === 0) return;
 
-    const numberParts = counters.slice(0, level).filter((n) => n > 0);
-    const numbering = numberParts.join(".");
-
-    const text = heading.textContent.trim().replace(/\s+/g, "_");
-    const id = `${numbering}_${text}`;
-    heading.id = id;
-
-    // Adjust nesting level
-    if (level > lastLevel) {
-      const newList = document.createElement("ul");
-      const lastLi =
-        currentListStack[currentListStack.length - 1].lastElementChild;
-      if (lastLi) lastLi.appendChild(newList);
-      currentListStack.push(newList);
-    } else if (level < lastLevel) {
-      currentListStack.splice(level - lastLevel);
-    }
-    lastLevel = level;
-
-    // Create TOC entry
-    const li = document.createElement("li");
-    const link = document.createElement("a");
-    link.href = `#${id}`;
-    link.textContent = `${numbering} ${heading.textContent.trim()}`;
-    li.appendChild(link);
-    currentListStack[currentListStack.length - 1].appendChild(li);
-  }
-
-  if (tocContainer) {
-    tocContainer.classList.remove("hidden");
-    tocToggleButton.classList.remove("hidden");
-    tocContainer.innerHTML = "";
-    tocContainer.appendChild(tocRoot);
-  }
+  tocContainer.classList.remove("hidden");
+  tocToggleButton.classList.remove("hidden");
   // Smooth scrolling
   document
     .querySelectorAll(`${containerSelector} a[href^="#"]`)
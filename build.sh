@@ -1,5 +1,6 @@
 #!/bin/bash
 set -euo pipefail
+cargo run --release --bin search_utils -- lint
 cd blog/tailwind && npm run build-css-prod && cd -
 cargo build --release --target x86_64-unknown-linux-musl --no-default-features --bin blog --bin search_utils
 cargo run --release --bin search_utils
@@ -15,6 +16,7 @@ cp target/x86_64-unknown-linux-musl/release/search_utils build/blog/
 cp -r blog/templates build/blog/
 cp -r blog/posts build/blog/
 cp -r search_utils/search build/search_utils
+cp site.toml build/
 find "./build" -type f -name ".DS_Store" | while read -r shit_file; do
     echo There is a shit, delete it: $shit_file
     rm $shit_file
@@ -1,21 +1,34 @@
 use crate::{
-    INDEX_DIR,
     cleaner::{md_to_plain, preprocess_text},
+    config::CONFIG,
     errors::SearchError,
-    jieba::JIEBA_ANALYZER,
-    post::{extract_md, find_all_frontmatters},
+    jieba::register_tokenizers,
+    manifest::{Manifest, hash},
+    post::{FrontMatter, extract_md, find_all_frontmatters, frontmatter_path, read_frontmatter},
 };
-use std::{fs, path::Path};
+use std::{collections::BTreeMap, fs, path::Path};
 use tantivy::{
-    Index, TantivyDocument,
+    Index, IndexWriter, TantivyDocument, Term,
     schema::{
-        Facet, FacetOptions, IndexRecordOption, STORED, Schema, TextFieldIndexing, TextOptions,
+        Facet, FacetOptions, Field, IndexRecordOption, STORED, STRING, Schema, TextFieldIndexing,
+        TextOptions,
     },
 };
 
-/// Create schema, register tokenizers, and index .txt files.
-pub fn build_index() -> Result<(), SearchError> {
-    // schema
+/// Post name to the hash of its document, kept in the index folder
+const INDEX_MANIFEST: &str = "manifest.toml";
+/// Stored as the payload of every commit, bump it when [`schema`] or the tokenizers change
+pub const SCHEMA_VERSION: u32 = 2;
+
+/// The text indexed into `content_zh` for a post
+pub(crate) fn post_text(fm: &FrontMatter) -> Result<String, SearchError> {
+    let content = extract_md(&fm.file_name)?;
+    let text = md_to_plain(&content);
+    let description = preprocess_text(&fm.description);
+    Ok(format!("{} {}", description, text))
+}
+
+fn schema() -> Schema {
     let mut schema_builder = Schema::builder();
 
     // prepare indexing options per-field with tokenizer name
@@ -27,56 +40,385 @@ pub fn build_index() -> Result<(), SearchError> {
         .set_indexing_options(zh_indexing)
         .set_stored();
 
-    let content_zh = schema_builder.add_text_field("content_zh", text_options_zh.clone());
-    let title_field = schema_builder.add_text_field("title", text_options_zh);
-    let tag_facet = schema_builder.add_facet_field("tags", FacetOptions::default());
-    let path_field = schema_builder.add_text_field("path", STORED);
+    schema_builder.add_text_field("content_zh", text_options_zh.clone());
+    schema_builder.add_text_field("title", text_options_zh);
+    schema_builder.add_facet_field("tags", FacetOptions::default());
+    // the title and content without stemming, its terms are shown as suggestions
+    let suggest_indexing = TextFieldIndexing::default()
+        .set_tokenizer("jieba_unstemmed")
+        .set_index_option(IndexRecordOption::Basic);
+    schema_builder.add_text_field(
+        "suggest",
+        TextOptions::default().set_indexing_options(suggest_indexing),
+    );
+    // indexed as a whole, so that the document of a post can be deleted
+    schema_builder.add_text_field("path", STRING | STORED);
+
+    schema_builder.build()
+}
+
+/// Whether an opened index can be searched and updated as [`build_index`] would build it
+pub fn check_index(index: &Index) -> Result<(), SearchError> {
+    let version = index.load_metas()?.payload;
+    if version.as_deref() != Some(SCHEMA_VERSION.to_string().as_str()) {
+        return Err(SearchError::internal(format!(
+            "The search index has schema version {}, {SCHEMA_VERSION} is expected",
+            version.as_deref().unwrap_or("none")
+        )));
+    }
+    if index.schema() != schema() {
+        return Err(SearchError::internal(format!(
+            "The search index has a schema different from version {SCHEMA_VERSION}"
+        )));
+    }
+    Ok(())
+}
+
+/// What a build wrote to the index
+#[derive(Debug, Default)]
+pub struct IndexChanges {
+    /// built from scratch, the index was missing or outdated
+    pub rebuilt: bool,
+    pub indexed: Vec<String>,
+    pub removed: Vec<String>,
+}
 
-    let schema = schema_builder.build();
+impl IndexChanges {
+    pub fn is_empty(&self) -> bool {
+        !self.rebuilt && self.indexed.is_empty() && self.removed.is_empty()
+    }
+}
 
-    // create index folder (overwrite if exists)
-    let index_path = Path::new(INDEX_DIR);
-    if index_path.exists() && index_path.is_dir() {
-        fs::remove_dir_all(index_path)?;
+/// The index and its manifest, changes are written by [`PostIndex::commit`]
+struct PostIndex {
+    writer: IndexWriter,
+    /// created by [`PostIndex::open`], so every post has to be written
+    created: bool,
+    manifest: Manifest,
+    content_zh: Field,
+    title: Field,
+    tags: Field,
+    suggest: Field,
+    path: Field,
+}
+
+impl PostIndex {
+    /// Open the index, it's created from scratch if [`open_current`] can not open it
+    fn open() -> Result<Self, SearchError> {
+        let index_path = Path::new(&CONFIG.paths.index);
+        let (index, manifest, created) = match open_current()? {
+            Some((index, manifest)) => (index, manifest, false),
+            None => {
+                if index_path.is_dir() {
+                    fs::remove_dir_all(index_path)?;
+                }
+                fs::create_dir_all(index_path)?;
+                let index = Index::create_in_dir(index_path, schema())?;
+                (index, Manifest::default(), true)
+            }
+        };
+        // Register jieba tokenizer for Chinese
+        register_tokenizers(&index);
+
+        let schema = index.schema();
+        Ok(Self {
+            writer: index.writer(50_000_000)?,
+            created,
+            manifest,
+            content_zh: schema.get_field("content_zh")?,
+            title: schema.get_field("title")?,
+            tags: schema.get_field("tags")?,
+            suggest: schema.get_field("suggest")?,
+            path: schema.get_field("path")?,
+        })
     }
-    fs::create_dir_all(index_path)?;
-    #[cfg(debug_assertions)]
-    let processed_path = index_path.join("processed_text");
-    #[cfg(debug_assertions)]
-    fs::create_dir(&processed_path)?;
-
-    let index = Index::create_in_dir(index_path, schema.clone())?;
-
-    // Register jieba tokenizer for Chinese
-    index.tokenizers().register("jieba", JIEBA_ANALYZER.clone());
-
-    // writer
-    let mut writer = index.writer(50_000_000)?;
-    let fms = find_all_frontmatters()?;
-    for fm in fms {
-        let content = extract_md(&fm.file_name)?;
-        let text = md_to_plain(&content);
-        let description = preprocess_text(&fm.description);
-        let text = format!("{} {}", description, text);
+
+    /// Write the document of a published post if it changed, returns whether it did
+    fn update(&mut self, document: &Document) -> Result<bool, SearchError> {
+        let Document { fm, text, hash } = document;
+        if self.manifest.posts.get(&fm.file_name) == Some(hash) {
+            return Ok(false);
+        }
         // save processed text for debugging
         #[cfg(debug_assertions)]
-        fs::write(
-            processed_path.join(&fm.file_name).with_extension("txt"),
-            &text,
-        )?;
+        {
+            let processed_path = Path::new(&CONFIG.paths.index).join("processed_text");
+            fs::create_dir_all(&processed_path)?;
+            fs::write(
+                processed_path.join(&fm.file_name).with_extension("txt"),
+                text,
+            )?;
+        }
+        self.writer
+            .delete_term(Term::from_field_text(self.path, &fm.file_name));
         let mut doc = TantivyDocument::default();
         for tag in fm.tags.iter() {
             let facet = Facet::from(&format!("/{}", tag.to_lowercase()));
-            doc.add_facet(tag_facet, facet);
+            doc.add_facet(self.tags, facet);
         }
         // store the same raw text into all analysis fields: per-field tokenizer will break it differently
-        doc.add_text(content_zh, &text);
-        doc.add_text(title_field, &fm.title);
-        doc.add_text(path_field, &fm.file_name);
-        writer.add_document(doc)?;
+        doc.add_text(self.content_zh, text);
+        doc.add_text(self.title, &fm.title);
+        doc.add_text(self.suggest, &fm.title);
+        doc.add_text(self.suggest, text);
+        doc.add_text(self.path, &fm.file_name);
+        self.writer.add_document(doc)?;
+        self.manifest
+            .posts
+            .insert(fm.file_name.clone(), hash.clone());
+        Ok(true)
     }
 
-    writer.commit()?;
-    println!("Index built at '{}'", INDEX_DIR);
-    Ok(())
+    /// Delete the document of a post, returns whether it was indexed
+    fn remove(&mut self, post_name: &str) -> bool {
+        self.writer
+            .delete_term(Term::from_field_text(self.path, post_name));
+        self.manifest.posts.remove(post_name).is_some()
+    }
+
+    fn commit(mut self) -> Result<(), SearchError> {
+        let mut commit = self.writer.prepare_commit()?;
+        commit.set_payload(&SCHEMA_VERSION.to_string());
+        commit.commit()?;
+        // deleted documents would still count in the term statistics, which scoring
+        // and suggestions rely on, merging the few segments of a blog drops them
+        let index = self.writer.index();
+        let segments = index.searchable_segment_metas()?;
+        if segments.len() > 1 || segments.iter().any(|segment| segment.has_deletes()) {
+            let ids = segments
+                .iter()
+                .map(|segment| segment.id())
+                .collect::<Vec<_>>();
+            self.writer.merge(&ids).wait()?;
+        }
+        self.writer.wait_merging_threads()?;
+        self.manifest
+            .save(Path::new(&CONFIG.paths.index).join(INDEX_MANIFEST))
+    }
+}
+
+/// The index, if it passes [`check_index`] and still has its manifest,
+/// without which the documents of deleted posts could not be found
+fn open_current() -> Result<Option<(Index, Manifest)>, SearchError> {
+    let index_path = Path::new(&CONFIG.paths.index);
+    if !index_path.join("meta.json").exists() {
+        return Ok(None);
+    }
+    let index = Index::open_in_dir(index_path)?;
+    let manifest = Manifest::load(index_path.join(INDEX_MANIFEST))?;
+    Ok(check_index(&index)
+        .ok()
+        .and(manifest)
+        .map(|manifest| (index, manifest)))
+}
+
+/// What is written into the document of a published post
+struct Document {
+    fm: FrontMatter,
+    text: String,
+    /// hash of everything written, kept in the manifest
+    hash: String,
+}
+
+impl Document {
+    fn new(fm: FrontMatter) -> Result<Self, SearchError> {
+        let text = post_text(&fm)?;
+        let hash = hash(
+            [fm.title.as_str()]
+                .into_iter()
+                .chain(fm.tags.iter().map(String::as_str))
+                .chain([text.as_str()]),
+        );
+        Ok(Self { fm, text, hash })
+    }
+}
+
+/// The documents of the published posts, keyed by post name
+fn published_documents() -> Result<BTreeMap<String, Document>, SearchError> {
+    find_all_frontmatters()?
+        .into_iter()
+        .filter(|fm| fm.is_published())
+        .map(|fm| Ok((fm.file_name.clone(), Document::new(fm)?)))
+        .collect()
+}
+
+/// Bring the index up to date with the posts, only new, changed and no longer
+/// published posts are written. A missing or outdated index is built from scratch.
+///
+/// An index that is already up to date is only read, no writer is opened.
+pub fn build_index() -> Result<IndexChanges, SearchError> {
+    let published = published_documents()?;
+    if let Some((_, manifest)) = open_current()?
+        && manifest.posts.len() == published.len()
+        && published
+            .iter()
+            .all(|(name, document)| manifest.posts.get(name) == Some(&document.hash))
+    {
+        return Ok(IndexChanges::default());
+    }
+    let mut index = PostIndex::open()?;
+    let changes = update_all(&mut index, &published)?;
+    if !changes.is_empty() {
+        index.commit()?;
+    }
+    Ok(changes)
+}
+
+fn update_all(
+    index: &mut PostIndex,
+    published: &BTreeMap<String, Document>,
+) -> Result<IndexChanges, SearchError> {
+    let mut changes = IndexChanges {
+        rebuilt: index.created,
+        ..Default::default()
+    };
+    let stale = index
+        .manifest
+        .posts
+        .keys()
+        .filter(|name| !published.contains_key(*name))
+        .cloned()
+        .collect::<Vec<_>>();
+    for name in stale {
+        index.remove(&name);
+        changes.removed.push(name);
+    }
+    for document in published.values() {
+        if index.update(document)? {
+            changes.indexed.push(document.fm.file_name.clone());
+        }
+    }
+    Ok(changes)
+}
+
+/// Update the document of a single post, it's removed if the post is deleted
+/// or no longer published. Without a usable index, one holding every post is built.
+///
+/// Returns whether the index changed.
+pub fn index_post(post_name: &str) -> Result<bool, SearchError> {
+    let path = frontmatter_path(post_name);
+    let fm = if path.exists() {
+        Some(toml::from_str::<FrontMatter>(&read_frontmatter(&path)?)?)
+    } else {
+        None
+    };
+    let mut index = PostIndex::open()?;
+    if index.created {
+        update_all(&mut index, &published_documents()?)?;
+        index.commit()?;
+        return Ok(true);
+    }
+    let changed = match fm {
+        Some(fm) if fm.is_published() => index.update(&Document::new(fm)?)?,
+        _ => index.remove(post_name),
+    };
+    index.commit()?;
+    Ok(changed)
+}
+
+#[cfg(test)]
+fn indexed_docs() -> (u64, bool) {
+    let index = Index::open_in_dir(&CONFIG.paths.index).unwrap();
+    let segments = index.searchable_segment_metas().unwrap();
+    let deleted = segments.iter().any(|segment| segment.has_deletes());
+    (segments.iter().map(|s| s.num_docs() as u64).sum(), deleted)
+}
+
+#[test]
+fn test_build_index() {
+    use crate::{post::post_dir, test_site};
+
+    let _site = test_site::site();
+    test_site::write_post("a", "A", "", "first post");
+    test_site::write_post("b", "B", "draft = true", "a draft");
+    let changes = build_index().unwrap();
+    assert!(changes.rebuilt);
+    assert_eq!(changes.indexed, ["a"]);
+    assert!(build_index().unwrap().is_empty());
+
+    test_site::write_post("a", "A", "", "first post, edited");
+    test_site::write_post("b", "B", "", "published now");
+    let changes = build_index().unwrap();
+    assert!(!changes.rebuilt);
+    assert_eq!(changes.indexed, ["a", "b"]);
+    assert_eq!(indexed_docs(), (2, false));
+
+    fs::remove_dir_all(post_dir("a")).unwrap();
+    assert_eq!(build_index().unwrap().removed, ["a"]);
+    assert_eq!(indexed_docs(), (1, false));
+
+    // without the manifest, the documents of deleted posts could not be found
+    fs::remove_file(Path::new(&CONFIG.paths.index).join(INDEX_MANIFEST)).unwrap();
+    let changes = build_index().unwrap();
+    assert!(changes.rebuilt);
+    assert_eq!(changes.indexed, ["b"]);
+}
+
+#[test]
+fn test_index_post() {
+    use crate::{post::post_dir, test_site};
+
+    let _site = test_site::site();
+    test_site::write_post("a", "A", "", "first post");
+    test_site::write_post("b", "B", "", "second post");
+    // a new index holds every post
+    assert!(index_post("a").unwrap());
+    assert_eq!(indexed_docs(), (2, false));
+    assert!(!index_post("b").unwrap());
+
+    test_site::write_post("b", "B", "", "second post, edited");
+    assert!(index_post("b").unwrap());
+    test_site::write_post("b", "B", "draft = true", "second post, edited");
+    assert!(index_post("b").unwrap());
+    assert_eq!(indexed_docs(), (1, false));
+
+    fs::remove_dir_all(post_dir("a")).unwrap();
+    assert!(index_post("a").unwrap());
+    assert!(!index_post("a").unwrap());
+    assert_eq!(indexed_docs(), (0, false));
+}
+
+#[test]
+fn test_current_index() {
+    use crate::test_site;
+
+    let _site = test_site::site();
+    test_site::write_post("a", "A", "", "first post");
+    build_index().unwrap();
+
+    // an up to date index is not written, even while another writer holds its lock
+    let index = Index::open_in_dir(&CONFIG.paths.index).unwrap();
+    let writer = index.writer::<TantivyDocument>(15_000_000).unwrap();
+    assert!(build_index().unwrap().is_empty());
+    drop(writer);
+
+    // an index built with another schema version is built again
+    let mut writer = index.writer::<TantivyDocument>(15_000_000).unwrap();
+    let mut commit = writer.prepare_commit().unwrap();
+    commit.set_payload(&(SCHEMA_VERSION - 1).to_string());
+    commit.commit().unwrap();
+    writer.wait_merging_threads().unwrap();
+    assert!(check_index(&index).is_err());
+    let changes = build_index().unwrap();
+    assert!(changes.rebuilt);
+    assert_eq!(changes.indexed, ["a"]);
+    check_index(&Index::open_in_dir(&CONFIG.paths.index).unwrap()).unwrap();
+}
+
+#[test]
+fn test_scheduled_post() {
+    use crate::test_site;
+
+    let _site = test_site::site();
+    let publish_at = chrono::Utc::now() + chrono::Duration::milliseconds(500);
+    let extra = format!("publish_at = \"{}\"", publish_at.to_rfc3339());
+    test_site::write_post("a", "A", &extra, "scheduled post");
+    assert!(build_index().unwrap().indexed.is_empty());
+
+    std::thread::sleep(
+        (publish_at - chrono::Utc::now())
+            .to_std()
+            .unwrap_or_default(),
+    );
+    assert_eq!(build_index().unwrap().indexed, ["a"]);
 }
@@ -1,4 +1,4 @@
-use crate::post::MD_OPTIONS;
+use crate::post::{MD_OPTIONS, strip_frontmatter};
 use comrak::{
     Arena,
     nodes::{AstNode, NodeValue},
@@ -8,7 +8,9 @@ use regex::Regex;
 use std::sync::LazyLock;
 
 /// Recursively walk the AST and collect only plain text.
-fn render_plain<'a>(node: &'a AstNode<'a>, output: &mut String) {
+///
+/// Code blocks go to `code` if given, or to `output` as well.
+fn render_plain<'a>(node: &'a AstNode<'a>, output: &mut String, mut code: Option<&mut String>) {
     for child in node.children() {
         match &child.data.borrow().value {
             NodeValue::Text(t) => output.push_str(t),
@@ -23,10 +25,11 @@ fn render_plain<'a>(node: &'a AstNode<'a>, output: &mut String) {
             | NodeValue::TableRow(_)
             | NodeValue::TableCell
             | NodeValue::FootnoteDefinition(_) => {
-                render_plain(child, output);
+                render_plain(child, output, code.as_deref_mut());
                 output.push('\n');
             }
             NodeValue::CodeBlock(block) => {
+                let output = code.as_deref_mut().unwrap_or(output);
                 output.push_str(&block.literal);
                 output.push('\n');
             }
@@ -38,21 +41,79 @@ fn render_plain<'a>(node: &'a AstNode<'a>, output: &mut String) {
             | NodeValue::Superscript
             | NodeValue::Subscript => {
                 // Just render the children, ignore formatting/URLs
-                render_plain(child, output);
+                render_plain(child, output, code.as_deref_mut());
             }
-            _ => render_plain(child, output),
+            _ => render_plain(child, output, code.as_deref_mut()),
         }
     }
 }
 
+/// Plain text of a post, the embedded frontmatter is skipped
 pub fn md_to_plain(md: &str) -> String {
     let arena = Arena::new();
-    let root = parse_document(&arena, md, &MD_OPTIONS);
+    let root = parse_document(&arena, strip_frontmatter(md), &MD_OPTIONS);
     let mut output = String::new();
-    render_plain(root, &mut output);
+    render_plain(root, &mut output, None);
     preprocess_text(&output)
 }
 
+const CJK_CHARS_PER_MINUTE: usize = 300;
+const LATIN_WORDS_PER_MINUTE: usize = 200;
+const CODE_LINES_PER_MINUTE: usize = 50;
+
+/// Size of a post, code blocks are counted by lines since they are read slower than prose
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct ReadingStats {
+    pub cjk_chars: usize,
+    pub latin_words: usize,
+    pub code_lines: usize,
+}
+
+impl ReadingStats {
+    pub fn new(md: &str) -> Self {
+        let arena = Arena::new();
+        let root = parse_document(&arena, md, &MD_OPTIONS);
+        let mut text = String::new();
+        let mut code = String::new();
+        render_plain(root, &mut text, Some(&mut code));
+        let mut stats = ReadingStats {
+            code_lines: code.lines().filter(|line| !line.trim().is_empty()).count(),
+            ..Default::default()
+        };
+        for word in preprocess_text(&text).split_whitespace() {
+            stats.cjk_chars += word.chars().filter(|c| is_cjk(*c)).count();
+            if word.chars().any(|c| c.is_alphanumeric() && !is_cjk(c)) {
+                stats.latin_words += 1;
+            }
+        }
+        stats
+    }
+
+    /// CJK characters and Latin words, code excluded
+    pub fn word_count(&self) -> usize {
+        self.cjk_chars + self.latin_words
+    }
+
+    /// Rounded up, at least one minute
+    pub fn minutes(&self) -> u32 {
+        let seconds = self.cjk_chars * 60 / CJK_CHARS_PER_MINUTE
+            + self.latin_words * 60 / LATIN_WORDS_PER_MINUTE
+            + self.code_lines * 60 / CODE_LINES_PER_MINUTE;
+        seconds.div_ceil(60).max(1) as u32
+    }
+}
+
+fn is_cjk(c: char) -> bool {
+    matches!(c,
+        '\u{3040}'..='\u{30ff}' // kana
+        | '\u{3400}'..='\u{4dbf}'
+        | '\u{4e00}'..='\u{9fff}'
+        | '\u{ac00}'..='\u{d7af}' // hangul
+        | '\u{f900}'..='\u{faff}'
+        | '\u{20000}'..='\u{2ffff}'
+    )
+}
+
 pub fn preprocess_text(text: &str) -> String {
     static RE1: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"([a-zA-Z])(\p{Han})").unwrap());
     static RE2: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"(\p{Han})([a-zA-Z])").unwrap());
@@ -61,3 +122,23 @@ pub fn preprocess_text(text: &str) -> String {
     let iter2 = RE2.replace_all(&iter1, "$1 $2");
     RE3.replace_all(&iter2, " ").to_string()
 }
+
+#[test]
+fn test_reading_stats() {
+    let md = "# 标题 Title\n\n中文text混排, two words.\n\n```rust\nlet a = 1;\n\nlet b = 2;\n```\n";
+    let stats = ReadingStats::new(md);
+    // `中文`, `标题` and `Title`, `text`, `two`, `words`, code is left out
+    assert_eq!(stats.cjk_chars, 6);
+    assert_eq!(stats.latin_words, 4);
+    assert_eq!(stats.code_lines, 2);
+    assert_eq!(stats.word_count(), 10);
+    assert_eq!(stats.minutes(), 1);
+
+    let long = ReadingStats {
+        cjk_chars: CJK_CHARS_PER_MINUTE * 3,
+        latin_words: LATIN_WORDS_PER_MINUTE,
+        code_lines: CODE_LINES_PER_MINUTE / 2,
+    };
+    assert_eq!(long.minutes(), 5);
+    assert_eq!(ReadingStats::new("").minutes(), 1);
+}
@@ -10,6 +10,10 @@ pub enum SearchError {
     Walker(#[from] ignore::Error),
     #[error("Toml parse error: {0}")]
     TomlDe(#[from] toml::de::Error),
+    #[error("Toml serialize error: {0}")]
+    TomlSer(#[from] toml::ser::Error),
+    #[error("Toml edit error: {0}")]
+    TomlEdit(#[from] toml_edit::TomlError),
     #[error("Internal error: {0}")]
     Internal(Cow<'static, str>),
 }
@@ -1,6 +1,6 @@
 use crate::{
     errors::SearchError,
-    post::{MD_OPTIONS, find_all_post_paths},
+    post::{MD_OPTIONS, find_all_post_paths, find_embedded_frontmatter},
 };
 use comrak::{
     Arena,
@@ -68,10 +68,13 @@ pub fn format_md_file(path: &Path) -> Result<(), String> {
     fs::write(path, res).map_err(|e| format!("write formatted md: {e}"))
 }
 
+/// The embedded frontmatter is kept untouched
 pub fn format_md(md: &str) -> Result<String, String> {
+    let body_start = find_embedded_frontmatter(md).map_or(0, |embedded| embedded.body_start);
+    let (frontmatter, body) = md.split_at(body_start);
     let arena = Arena::new();
 
-    let root = parse_document(&arena, md, &MD_OPTIONS);
+    let root = parse_document(&arena, body, &MD_OPTIONS);
 
     recursion_nodes(root, &|node| {
         let mut data = node.data.borrow_mut();
@@ -92,6 +95,10 @@ pub fn format_md(md: &str) -> Result<String, String> {
     })?;
 
     let mut output = String::with_capacity(md.len());
+    if !frontmatter.is_empty() {
+        output.push_str(frontmatter);
+        output.push('\n');
+    }
     format_commonmark(root, &MD_OPTIONS, &mut output).map_err(|e| e.to_string())?;
     Ok(output)
 }
@@ -146,3 +153,11 @@ fn test_fmt_md() {
     let formatted = format_md(&md).unwrap();
     println!("{}", formatted)
 }
+
+#[test]
+fn test_fmt_frontmatter() {
+    let md = "+++\ntitle =  'kept'   # as is\n+++\n\n*  item\n";
+    let formatted = format_md(md).unwrap();
+    assert!(formatted.starts_with("+++\ntitle =  'kept'   # as is\n+++\n\n"));
+    assert_eq!(format_md(&formatted).unwrap(), formatted);
+}
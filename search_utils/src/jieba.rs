@@ -1,8 +1,11 @@
 use std::sync::LazyLock;
 
-use tantivy::tokenizer::{
-    LowerCaser, RemoveLongFilter, Stemmer, StopWordFilter, TextAnalyzer, Token, TokenStream,
-    Tokenizer,
+use tantivy::{
+    Index,
+    tokenizer::{
+        LowerCaser, RemoveLongFilter, Stemmer, StopWordFilter, TextAnalyzer, Token, TokenStream,
+        Tokenizer,
+    },
 };
 
 pub trait JiebaTokenize {
@@ -120,6 +123,23 @@ pub static JIEBA_ANALYZER: LazyLock<TextAnalyzer> = LazyLock::new(|| {
         .build()
 });
 
+/// Words as written, lowercased, for the suggestions of the search box
+pub static JIEBA_ANALYZER_UNSTEMMED: LazyLock<TextAnalyzer> = LazyLock::new(|| {
+    tantivy::tokenizer::TextAnalyzer::builder(JiebaTokenizer::with_mode(JiebaMode::CutAll))
+        .filter(RemoveLongFilter::limit(40))
+        .filter(LowerCaser)
+        .filter(StopWordFilter::new(tantivy::tokenizer::Language::English).unwrap())
+        .build()
+});
+
+/// Register the analyzers named in the schema of the index
+pub fn register_tokenizers(index: &Index) {
+    index.tokenizers().register("jieba", JIEBA_ANALYZER.clone());
+    index
+        .tokenizers()
+        .register("jieba_unstemmed", JIEBA_ANALYZER_UNSTEMMED.clone());
+}
+
 pub static JIEBA_ANALYZER_SEARCH: LazyLock<TextAnalyzer> = LazyLock::new(|| {
     tantivy::tokenizer::TextAnalyzer::builder(JiebaTokenizer::with_mode(JiebaMode::Search))
         .filter(RemoveLongFilter::limit(40))
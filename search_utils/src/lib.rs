@@ -1,30 +1,35 @@
 use std::{collections::HashSet, fs, sync::LazyLock};
 
-use const_format::formatcp;
+use crate::config::CONFIG;
 
 pub mod build_index;
 pub mod cleaner;
 pub mod errors;
 pub mod formatter;
 pub mod jieba;
+pub mod lint;
 pub mod lock;
+pub mod manifest;
+pub mod migrate;
+pub mod new_post;
 pub mod post;
 pub mod search;
+#[cfg(any(test, feature = "test-site"))]
+pub mod test_site;
 pub mod timestamp;
 
-const SEARCH_PATH: &str = "./search_utils";
-pub const BLOG_PATH: &str = "./blog";
+pub use site_config as config;
 
+/// A path under `paths.blog` of the site config
 #[macro_export]
 macro_rules! blog_path {
     ($name:literal) => {
-        const_format::formatcp!("{}{}", $crate::BLOG_PATH, $name)
+        format!("{}{}", $crate::config::CONFIG.paths.blog, $name)
     };
 }
 
 static STOP_WORDS: LazyLock<HashSet<String>> = LazyLock::new(|| {
-    let file = fs::read_to_string(formatcp!("{}/search/cn_stopwords.txt", SEARCH_PATH)).unwrap();
+    let file =
+        fs::read_to_string(format!("{}/search/cn_stopwords.txt", CONFIG.paths.search)).unwrap();
     file.lines().map(|s| s.to_string()).collect()
 });
-
-pub const INDEX_DIR: &str = formatcp!("{}/search/data", SEARCH_PATH);
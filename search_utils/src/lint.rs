@@ -0,0 +1,363 @@
+use crate::{
+    errors::SearchError,
+    post::{
+        FRONTMATTER_DELIMITER, FrontMatter, IMG_RE, MD_OPTIONS, POST_FRONTMATTER, SRC_RE,
+        find_all_frontmatter_paths, find_all_post_paths, find_embedded_frontmatter,
+        read_frontmatter, static_file,
+    },
+};
+use comrak::{
+    Arena,
+    nodes::{AstNode, NodeValue},
+    parse_document,
+};
+use regex::Regex;
+use serde::Serialize;
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt, fs,
+    path::{Path, PathBuf},
+    sync::LazyLock,
+};
+
+static ALT_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r#"(?i)\balt\s*=\s*["']([^"']*)["']"#).unwrap());
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Issue {
+    pub severity: Severity,
+    pub path: PathBuf,
+    /// 1-based line in the file
+    pub line: Option<usize>,
+    pub message: String,
+}
+
+impl fmt::Display for Issue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let severity = match self.severity {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        };
+        write!(f, "{severity}: {}", self.path.display())?;
+        if let Some(line) = self.line {
+            write!(f, ":{line}")?;
+        }
+        write!(f, ": {}", self.message)
+    }
+}
+
+#[derive(Default)]
+struct Issues(Vec<Issue>);
+
+impl Issues {
+    fn push(&mut self, severity: Severity, path: &Path, line: Option<usize>, message: String) {
+        self.0.push(Issue {
+            severity,
+            path: path.to_path_buf(),
+            line,
+            message,
+        });
+    }
+
+    fn error(&mut self, path: &Path, line: Option<usize>, message: String) {
+        self.push(Severity::Error, path, line, message);
+    }
+
+    fn warning(&mut self, path: &Path, line: Option<usize>, message: String) {
+        self.push(Severity::Warning, path, line, message);
+    }
+}
+
+/// Check every frontmatter and post, sorted by path and line
+pub fn lint() -> Result<Vec<Issue>, SearchError> {
+    let mut issues = Issues::default();
+    let mut file_names = HashMap::<String, PathBuf>::new();
+    // lowercase tag -> spelling -> frontmatters using it
+    let mut tags = BTreeMap::<String, BTreeMap<String, Vec<PathBuf>>>::new();
+    for path in find_all_frontmatter_paths()? {
+        let fm = match toml::from_str::<FrontMatter>(&read_frontmatter(&path)?) {
+            Ok(fm) => fm,
+            Err(e) => {
+                issues.error(&path, None, format!("invalid frontmatter: {e}"));
+                continue;
+            }
+        };
+        lint_frontmatter(&path, &fm, &mut issues);
+        if let Some(first) = file_names.insert(fm.file_name.clone(), path.clone()) {
+            issues.error(
+                &path,
+                None,
+                format!(
+                    "file_name '{}' is also used by {}",
+                    fm.file_name,
+                    first.display()
+                ),
+            );
+        }
+        for tag in fm.tags {
+            tags.entry(tag.to_lowercase())
+                .or_default()
+                .entry(tag)
+                .or_default()
+                .push(path.clone());
+        }
+    }
+    for spellings in tags.values().filter(|spellings| spellings.len() > 1) {
+        // the most used spelling is taken as the right one
+        let Some((common, _)) = spellings.iter().max_by_key(|(_, paths)| paths.len()) else {
+            continue;
+        };
+        for (spelling, paths) in spellings.iter().filter(|(spelling, _)| *spelling != common) {
+            for path in paths {
+                issues.warning(
+                    path,
+                    None,
+                    format!("tag '{spelling}' differs from '{common}' only in case"),
+                );
+            }
+        }
+    }
+    for path in find_all_post_paths()? {
+        let md = fs::read_to_string(&path)?;
+        let body_start = match find_embedded_frontmatter(&md) {
+            Some(embedded) => {
+                if path.with_file_name(POST_FRONTMATTER).exists() {
+                    issues.error(
+                        &path,
+                        Some(1),
+                        format!("frontmatter is ignored, {POST_FRONTMATTER} takes precedence"),
+                    );
+                }
+                embedded.body_start
+            }
+            None if md.starts_with(FRONTMATTER_DELIMITER) => {
+                issues.error(&path, Some(1), "frontmatter is not closed".to_string());
+                continue;
+            }
+            None => 0,
+        };
+        let (frontmatter, body) = md.split_at(body_start);
+        lint_markdown(&path, frontmatter.lines().count(), body, &mut issues);
+    }
+    let mut issues = issues.0;
+    issues.sort_by(|a, b| (&a.path, a.line).cmp(&(&b.path, b.line)));
+    Ok(issues)
+}
+
+fn lint_frontmatter(path: &Path, fm: &FrontMatter, issues: &mut Issues) {
+    let dir_name = path
+        .parent()
+        .and_then(Path::file_name)
+        .and_then(|name| name.to_str());
+    if dir_name != Some(fm.file_name.as_str()) {
+        issues.error(
+            path,
+            None,
+            format!(
+                "file_name '{}' does not match the directory '{}'",
+                fm.file_name,
+                dir_name.unwrap_or_default()
+            ),
+        );
+    }
+    if let Some(cover) = &fm.cover_image
+        && let Err(message) = check_image(cover)
+    {
+        issues.error(path, None, format!("cover_image {message}"));
+    }
+    if *fm.updated < *fm.posted {
+        issues.error(
+            path,
+            None,
+            format!(
+                "updated {} is earlier than posted {}",
+                fm.updated, fm.posted
+            ),
+        );
+    }
+}
+
+/// `line_offset` is the number of lines before `md` in the file
+fn lint_markdown(path: &Path, line_offset: usize, md: &str, issues: &mut Issues) {
+    let arena = Arena::new();
+    let root = parse_document(&arena, md, &MD_OPTIONS);
+    // the title of the post is the only h1
+    let mut last_level = 1;
+    for node in root.descendants() {
+        let ast = node.data.borrow();
+        let line = line_offset + ast.sourcepos.start.line;
+        match &ast.value {
+            NodeValue::Heading(heading) => {
+                if heading.level > last_level + 1 {
+                    issues.warning(
+                        path,
+                        Some(line),
+                        format!(
+                            "heading level skips from h{last_level} to h{}",
+                            heading.level
+                        ),
+                    );
+                }
+                last_level = heading.level;
+            }
+            NodeValue::Image(link) => {
+                if let Err(message) = check_image(&link.url) {
+                    issues.error(path, Some(line), format!("image {message}"));
+                }
+                if plain_text(node).trim().is_empty() {
+                    issues.warning(
+                        path,
+                        Some(line),
+                        format!("image '{}' has no alt text", link.url),
+                    );
+                }
+            }
+            NodeValue::HtmlBlock(block) => {
+                for (i, html) in block.literal.lines().enumerate() {
+                    lint_html(path, line + i, html, issues);
+                }
+            }
+            NodeValue::HtmlInline(html) => lint_html(path, line, html, issues),
+            _ => (),
+        }
+    }
+}
+
+/// `<img>` tags written as raw html
+fn lint_html(path: &Path, line: usize, html: &str, issues: &mut Issues) {
+    for img in IMG_RE.find_iter(html).map(|m| m.as_str()) {
+        let src = SRC_RE.captures(img).map(|c| c[1].to_string());
+        if let Some(src) = &src
+            && let Err(message) = check_image(src)
+        {
+            issues.error(path, Some(line), format!("image {message}"));
+        }
+        if ALT_RE.captures(img).is_none_or(|c| c[1].trim().is_empty()) {
+            let src = src.unwrap_or_default();
+            issues.warning(path, Some(line), format!("image '{src}' has no alt text"));
+        }
+    }
+}
+
+/// Local images must exist under `blog/static`, remote ones are not checked
+fn check_image(url: &str) -> Result<(), String> {
+    if url.contains("://") || url.starts_with("//") || url.starts_with("data:") {
+        return Ok(());
+    }
+    if static_file(url).is_some_and(|file| file.is_file()) {
+        Ok(())
+    } else {
+        Err(format!("'{url}' does not exist under blog/static"))
+    }
+}
+
+fn plain_text<'a>(node: &'a AstNode<'a>) -> String {
+    node.descendants()
+        .filter_map(|n| match &n.data.borrow().value {
+            NodeValue::Text(t) => Some(t.to_string()),
+            NodeValue::Code(c) => Some(c.literal.clone()),
+            _ => None,
+        })
+        .collect()
+}
+
+#[test]
+fn test_lint() {
+    use crate::{
+        blog_path,
+        post::{POST_MD, post_dir},
+        test_site,
+    };
+
+    let _site = test_site::site();
+    fs::write(blog_path!("/static/cat.png"), b"").unwrap();
+    test_site::write_post(
+        "clean",
+        "Clean",
+        "cover_image = \"/static/cat.png\"",
+        "## Cat\n\n![a cat](/static/cat.png)\n\n<img src=\"/static/cat.png\" alt=\"a cat\">\n",
+    );
+    test_site::write_post(
+        "images",
+        "Images",
+        "",
+        "### Deep\n\n![](/static/dog.png)\n\n<img src=\"/static/cat.png\">\n\n\
+         ![outside](/static/../posts/clean/post.md)\n",
+    );
+    test_site::write_post("shadowed", "Shadowed", "", "body\n");
+    let extra = "file_name = \"clean\"\nposted = \"2025-01-02T00:00:00+08:00\"\ntags = [\"rust\"]";
+    fs::write(
+        post_dir("shadowed").join(POST_FRONTMATTER),
+        test_site::frontmatter_toml("shadowed", extra),
+    )
+    .unwrap();
+    fs::create_dir_all(post_dir("unclosed")).unwrap();
+    fs::write(
+        post_dir("unclosed").join(POST_MD),
+        "+++\ntitle = \"Unclosed\"\n",
+    )
+    .unwrap();
+
+    let issues = lint()
+        .unwrap()
+        .into_iter()
+        .map(|issue| {
+            let post = issue.path.parent().unwrap().file_name().unwrap();
+            let post = post.to_string_lossy().into_owned();
+            (post, issue.severity, issue.message)
+        })
+        .collect::<Vec<_>>();
+    let has = |post: &str, severity: Severity, message: &str| {
+        issues
+            .iter()
+            .any(|issue| issue.0 == post && issue.1 == severity && issue.2.contains(message))
+    };
+    // the toml frontmatters are read before the embedded ones, so shadowed keeps the name
+    assert!(has("clean", Severity::Error, "is also used by"));
+    assert!(has("images", Severity::Warning, "skips from h1 to h3"));
+    assert!(has(
+        "images",
+        Severity::Error,
+        "image '/static/dog.png' does not exist"
+    ));
+    assert!(has(
+        "images",
+        Severity::Warning,
+        "image '/static/dog.png' has no alt text"
+    ));
+    assert!(has(
+        "images",
+        Severity::Warning,
+        "image '/static/cat.png' has no alt text"
+    ));
+    assert!(has(
+        "images",
+        Severity::Error,
+        "image '/static/../posts/clean/post.md' does not exist"
+    ));
+    assert!(has(
+        "shadowed",
+        Severity::Error,
+        "does not match the directory"
+    ));
+    assert!(has("shadowed", Severity::Error, "is earlier than posted"));
+    assert!(has(
+        "shadowed",
+        Severity::Warning,
+        "'rust' differs from 'Rust'"
+    ));
+    assert!(has("shadowed", Severity::Error, "frontmatter is ignored"));
+    assert!(has(
+        "unclosed",
+        Severity::Error,
+        "frontmatter is not closed"
+    ));
+    assert_eq!(issues.len(), 11, "{issues:?}");
+}
@@ -1,7 +1,5 @@
-#[cfg(debug_assertions)]
 use std::ops::{Deref, DerefMut};
-#[cfg(debug_assertions)]
-use std::sync::{RwLock, RwLockReadGuard, RwLockWriteGuard};
+use std::sync::{PoisonError, RwLock, RwLockReadGuard, RwLockWriteGuard};
 
 #[cfg(not(debug_assertions))]
 pub struct Lock<T>(T);
@@ -20,12 +18,10 @@ impl<T> Lock<T> {
 #[cfg(debug_assertions)]
 pub struct Lock<T>(RwLock<T>);
 
-#[cfg(debug_assertions)]
 pub struct ReadGuard<'a, T> {
     guard: RwLockReadGuard<'a, T>,
 }
 
-#[cfg(debug_assertions)]
 pub struct WriteGuard<'a, T> {
     guard: RwLockWriteGuard<'a, T>,
 }
@@ -46,7 +42,6 @@ impl<T> Lock<T> {
     }
 }
 
-#[cfg(debug_assertions)]
 impl<T> Deref for ReadGuard<'_, T> {
     type Target = T;
 
@@ -55,7 +50,6 @@ impl<T> Deref for ReadGuard<'_, T> {
     }
 }
 
-#[cfg(debug_assertions)]
 impl<T> Deref for WriteGuard<'_, T> {
     type Target = T;
 
@@ -64,9 +58,27 @@ impl<T> Deref for WriteGuard<'_, T> {
     }
 }
 
-#[cfg(debug_assertions)]
 impl<T> DerefMut for WriteGuard<'_, T> {
     fn deref_mut(&mut self) -> &mut Self::Target {
         &mut self.guard
     }
 }
+
+/// A [`Lock`] that can be written in release builds too,
+/// for what changes while the server runs, e.g. once a scheduled post is published
+pub struct Shared<T>(RwLock<T>);
+
+impl<T> Shared<T> {
+    pub fn new(inner: T) -> Self {
+        Shared(RwLock::new(inner))
+    }
+    pub fn get(&self) -> ReadGuard<'_, T> {
+        let guard = self.0.read().unwrap_or_else(PoisonError::into_inner);
+        ReadGuard { guard }
+    }
+
+    pub fn get_mut(&self) -> WriteGuard<'_, T> {
+        let guard = self.0.write().unwrap_or_else(PoisonError::into_inner);
+        WriteGuard { guard }
+    }
+}
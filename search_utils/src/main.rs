@@ -1,9 +1,106 @@
-use search_utils::{build_index::build_index, errors::SearchError, formatter};
+use clap::{Parser, Subcommand, ValueEnum};
+use search_utils::{
+    build_index::build_index,
+    config::CONFIG,
+    errors::SearchError,
+    formatter,
+    lint::{Severity, lint},
+    manifest::bump_all_updated,
+    migrate::embed_all_frontmatters,
+    new_post::new_post,
+};
+
+#[derive(Debug, Parser)]
+#[command(about = "Search index and post tools of lhz07's blog")]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Bump `updated` of changed posts and build the search index, the default command
+    Build,
+    /// Format the markdown of every post
+    Fmt,
+    /// Scaffold a new draft post
+    New {
+        title: String,
+        /// Comma separated tags
+        #[arg(short, long, value_delimiter = ',')]
+        tags: Vec<String>,
+        /// Folder name of the post, ASCII letters, digits, `-` and `_`,
+        /// derived from the title by default
+        #[arg(long)]
+        slug: Option<String>,
+    },
+    /// Check every post and frontmatter, exit non-zero on any error
+    Lint {
+        #[arg(long, value_enum, default_value_t = Format::Human)]
+        format: Format,
+    },
+    /// Move every `post_frontmatter.toml` into a `+++` block at the top of its `post.md`
+    Migrate,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum Format {
+    Human,
+    Json,
+}
 
 fn main() -> Result<(), SearchError> {
-    let mut arg = std::env::args();
-    match arg.nth(1) {
-        Some(s) if s == "fmt" => formatter::format_all(),
-        _ => build_index(),
+    match Cli::parse().command.unwrap_or(Command::Build) {
+        Command::Build => {
+            for name in bump_all_updated()? {
+                println!("Bumped `updated` of '{name}'");
+            }
+            let changes = build_index()?;
+            if changes.rebuilt {
+                println!("Rebuilt the index from scratch");
+            }
+            for name in changes.removed {
+                println!("Removed '{name}' from the index");
+            }
+            for name in changes.indexed {
+                println!("Indexed '{name}'");
+            }
+            println!("Index built at '{}'", CONFIG.paths.index);
+            Ok(())
+        }
+        Command::Fmt => formatter::format_all(),
+        Command::New { title, tags, slug } => {
+            let dir = new_post(&title, &tags, slug)?;
+            println!("Created draft '{}'", dir.display());
+            Ok(())
+        }
+        Command::Lint { format } => {
+            let issues = lint()?;
+            match format {
+                Format::Human => issues.iter().for_each(|issue| println!("{issue}")),
+                Format::Json => println!(
+                    "{}",
+                    serde_json::to_string_pretty(&issues)
+                        .map_err(|e| SearchError::internal(e.to_string()))?
+                ),
+            }
+            let errors = issues
+                .iter()
+                .filter(|issue| issue.severity == Severity::Error)
+                .count();
+            if let Format::Human = format {
+                println!("{errors} errors, {} warnings", issues.len() - errors);
+            }
+            if errors > 0 {
+                return Err(SearchError::internal(format!("{errors} lint errors")));
+            }
+            Ok(())
+        }
+        Command::Migrate => {
+            for path in embed_all_frontmatters()? {
+                println!("Migrated '{}'", path.display());
+            }
+            Ok(())
+        }
     }
 }
@@ -0,0 +1,209 @@
+use crate::{
+    config::CONFIG,
+    errors::SearchError,
+    post::{
+        MD_OPTIONS, POST_MD, find_all_post_paths, find_embedded_frontmatter, frontmatter_path,
+        post_dir, strip_frontmatter,
+    },
+    timestamp::now_rfc3339,
+};
+use comrak::{Arena, format_commonmark, nodes::NodeValue, parse_document};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::{collections::BTreeMap, fs, io, path::Path};
+use toml_edit::{DocumentMut, Item, Value};
+
+/// Version of [`hash`] and [`content_hash`], manifests written with another one are discarded
+const HASH_VERSION: u32 = 2;
+
+/// Hash of `parts`, each one is ended by a NUL so that moving a boundary changes the hash
+pub fn hash<'a>(parts: impl IntoIterator<Item = &'a str>) -> String {
+    let mut hasher = Sha256::new();
+    for part in parts {
+        hasher.update(part.as_bytes());
+        hasher.update([0]);
+    }
+    format!("{:x}", hasher.finalize())
+}
+
+/// Hash of the markdown as the formatter would write it, ignoring whitespace,
+/// so that a formatter rewrite is not a content change.
+///
+/// Whitespace in code blocks counts, except in the `rust` ones rewritten by rustfmt.
+pub fn content_hash(md: &str) -> Result<String, SearchError> {
+    let arena = Arena::new();
+    let root = parse_document(&arena, md, &MD_OPTIONS);
+    let mut normalized = String::with_capacity(md.len());
+    format_commonmark(root, &MD_OPTIONS, &mut normalized)
+        .map_err(|e| SearchError::internal(e.to_string()))?;
+    let mut code = Vec::new();
+    for node in root.descendants() {
+        if let NodeValue::CodeBlock(block) = &node.data.borrow().value
+            && !block.info.trim().eq_ignore_ascii_case("rust")
+        {
+            code.push(block.literal.clone());
+        }
+    }
+    let words = normalized.split_whitespace();
+    Ok(hash(words.chain(code.iter().map(String::as_str))))
+}
+
+/// Post name to a hash of it, saved as TOML
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Manifest {
+    version: u32,
+    pub posts: BTreeMap<String, String>,
+}
+
+impl Default for Manifest {
+    fn default() -> Self {
+        Self {
+            version: HASH_VERSION,
+            posts: BTreeMap::new(),
+        }
+    }
+}
+
+impl Manifest {
+    /// `None` if the file is missing, unreadable as a manifest, or hashed differently
+    pub fn load(path: impl AsRef<Path>) -> Result<Option<Self>, SearchError> {
+        match fs::read_to_string(path) {
+            Ok(content) => Ok(toml::from_str::<Manifest>(&content)
+                .ok()
+                .filter(|manifest| manifest.version == HASH_VERSION)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), SearchError> {
+        fs::write(path, toml::to_string(self)?)?;
+        Ok(())
+    }
+}
+
+/// Bump `updated` of the posts whose content changed since they were last seen,
+/// returns the bumped posts.
+///
+/// Posts seen for the first time, or since the hashing changed, are only recorded.
+/// Names without a `post.md` are skipped.
+pub fn bump_updated<'a>(
+    post_names: impl IntoIterator<Item = &'a str>,
+) -> Result<Vec<String>, SearchError> {
+    let path = &CONFIG.paths.content_hashes;
+    let mut manifest = Manifest::load(path)?.unwrap_or_default();
+    let mut changed = false;
+    let mut bumped = Vec::new();
+    for name in post_names {
+        let md = match fs::read_to_string(post_dir(name).join(POST_MD)) {
+            Ok(md) => md,
+            // not a post, or removed since, the other posts are still bumped
+            Err(e) if e.kind() == io::ErrorKind::NotFound => continue,
+            Err(e) => return Err(e.into()),
+        };
+        let hash = content_hash(strip_frontmatter(&md))?;
+        match manifest.posts.get(name) {
+            Some(old) if *old == hash => continue,
+            Some(_) => {
+                set_updated(&frontmatter_path(name), &now_rfc3339()?)?;
+                bumped.push(name.to_string());
+            }
+            None => (),
+        }
+        manifest.posts.insert(name.to_string(), hash);
+        changed = true;
+    }
+    if changed {
+        manifest.save(path)?;
+    }
+    Ok(bumped)
+}
+
+/// [`bump_updated`] for every post
+pub fn bump_all_updated() -> Result<Vec<String>, SearchError> {
+    let paths = find_all_post_paths()?;
+    let names = paths
+        .iter()
+        .filter(|path| path.ends_with(POST_MD))
+        .filter_map(|path| path.parent()?.file_name()?.to_str());
+    bump_updated(names)
+}
+
+/// Rewrite `updated` in place, keeping the comments, key order and quotes
+fn set_updated(path: &Path, now: &str) -> Result<(), SearchError> {
+    let mut content = fs::read_to_string(path)?;
+    // only the TOML embedded in post.md is edited
+    let toml = match find_embedded_frontmatter(&content) {
+        Some(embedded) if path.ends_with(POST_MD) => embedded.toml,
+        _ => 0..content.len(),
+    };
+    let mut doc = content[toml.clone()].parse::<DocumentMut>()?;
+    let old = doc.get("updated").and_then(Item::as_value);
+    // timestamps have nothing to escape, so the old quotes can be reused
+    let quote = match old {
+        Some(Value::String(s)) => s
+            .as_repr()
+            .and_then(|repr| repr.as_raw().as_str())
+            .and_then(|raw| raw.chars().next()),
+        _ => None,
+    }
+    .unwrap_or('"');
+    let mut updated = format!("{quote}{now}{quote}").parse::<Value>()?;
+    if let Some(old) = old {
+        *updated.decor_mut() = old.decor().clone();
+    }
+    doc["updated"] = Item::Value(updated);
+    content.replace_range(toml, &doc.to_string());
+    fs::write(path, content)?;
+    Ok(())
+}
+
+#[test]
+fn test_content_hash() {
+    let hash = |md| content_hash(md).unwrap();
+    assert_eq!(hash("*  item\n\ntext\n"), hash("* item\n\n\ntext"));
+    assert_ne!(hash("foo bar\n"), hash("foobar\n"));
+    assert_ne!(hash("```\na  b\n```\n"), hash("```\na b\n```\n"));
+    assert_eq!(
+        hash("```rust\nlet  a = 1;\n```\n"),
+        hash("```rust\nlet a = 1;\n```\n")
+    );
+}
+
+#[test]
+fn test_bump_updated() {
+    use crate::{
+        post::{FrontMatter, read_frontmatter},
+        test_site,
+    };
+
+    let _site = test_site::site();
+    let updated = || {
+        let toml = read_frontmatter(&frontmatter_path("a")).unwrap();
+        toml::from_str::<FrontMatter>(&toml)
+            .unwrap()
+            .updated
+            .to_rfc3339()
+    };
+    test_site::write_post("a", "A", "", "first post\n");
+    let original = updated();
+    // the first sight is recorded, a missing post is skipped
+    assert!(bump_updated(["a", "missing"]).unwrap().is_empty());
+    assert!(
+        Manifest::load(&CONFIG.paths.content_hashes)
+            .unwrap()
+            .unwrap()
+            .posts
+            .contains_key("a")
+    );
+
+    // formatting does not count as a change
+    test_site::write_post("a", "A", "", "first   post\n\n");
+    assert!(bump_updated(["a"]).unwrap().is_empty());
+    assert_eq!(updated(), original);
+
+    test_site::write_post("a", "A", "", "first post, edited\n");
+    assert_eq!(bump_updated(["a"]).unwrap(), ["a"]);
+    assert_ne!(updated(), original);
+    assert!(bump_updated(["a"]).unwrap().is_empty());
+}
@@ -0,0 +1,35 @@
+use crate::{
+    errors::SearchError,
+    post::{
+        POST_FRONTMATTER, POST_MD, embed_frontmatter, find_all_frontmatter_paths,
+        find_embedded_frontmatter,
+    },
+};
+use std::{fs, path::PathBuf};
+
+/// Move every `post_frontmatter.toml` to the top of its `post.md`, returns the migrated posts
+pub fn embed_all_frontmatters() -> Result<Vec<PathBuf>, SearchError> {
+    let mut migrated = Vec::new();
+    for path in find_all_frontmatter_paths()? {
+        if !path.ends_with(POST_FRONTMATTER) {
+            continue;
+        }
+        let md_path = path.with_file_name(POST_MD);
+        let md = match fs::read_to_string(&md_path) {
+            Ok(md) => md,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+            Err(e) => return Err(e.into()),
+        };
+        if find_embedded_frontmatter(&md).is_some() {
+            return Err(SearchError::internal(format!(
+                "'{}' already has a frontmatter",
+                md_path.display()
+            )));
+        }
+        let toml = fs::read_to_string(&path)?;
+        fs::write(&md_path, embed_frontmatter(&toml, &md))?;
+        fs::remove_file(&path)?;
+        migrated.push(md_path);
+    }
+    Ok(migrated)
+}
@@ -0,0 +1,118 @@
+use crate::{
+    blog_path,
+    config::CONFIG,
+    errors::SearchError,
+    post::{POST_MD, embed_frontmatter, post_dir},
+    timestamp::now_rfc3339,
+};
+use serde::Serialize;
+use std::{fs, path::PathBuf};
+
+/// Frontmatter of a new post, `TimeStamp` is serialized for display instead of RFC 3339
+#[derive(Debug, Serialize)]
+struct NewFrontMatter<'a> {
+    title: &'a str,
+    file_name: &'a str,
+    description: &'a str,
+    tags: &'a [String],
+    posted: &'a str,
+    updated: &'a str,
+    author: &'a str,
+    draft: bool,
+}
+
+/// Lowercase ASCII words joined by `-`, Chinese is transliterated to pinyin
+pub fn slugify(title: &str) -> String {
+    let ascii = deunicode::deunicode(title).to_lowercase();
+    ascii
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+fn is_valid_slug(slug: &str) -> bool {
+    !slug.is_empty()
+        && slug
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+/// Create the post folder, a `post.md` holding only the frontmatter, and the image folder.
+///
+/// The post starts as a draft, returns the post folder.
+pub fn new_post(
+    title: &str,
+    tags: &[String],
+    slug: Option<String>,
+) -> Result<PathBuf, SearchError> {
+    let slug = match slug {
+        // the folder and url of the post, so no separators or `..`
+        Some(slug) if !is_valid_slug(&slug) => {
+            return Err(SearchError::internal(format!(
+                "Invalid slug '{slug}', use ASCII letters, digits, '-' and '_'"
+            )));
+        }
+        Some(slug) => slug,
+        None => slugify(title),
+    };
+    if slug.is_empty() {
+        return Err(SearchError::internal(format!(
+            "Can not derive a slug from '{title}', pass one with --slug"
+        )));
+    }
+    let dir = post_dir(&slug);
+    if dir.exists() {
+        return Err(SearchError::internal(format!(
+            "Post '{}' already exists",
+            dir.display()
+        )));
+    }
+    let now = now_rfc3339()?;
+    let frontmatter = NewFrontMatter {
+        title,
+        file_name: &slug,
+        description: "",
+        tags,
+        posted: &now,
+        updated: &now,
+        author: &CONFIG.site.author,
+        draft: true,
+    };
+    let content = toml::to_string(&frontmatter)?;
+
+    fs::create_dir_all(&dir)?;
+    fs::write(dir.join(POST_MD), embed_frontmatter(&content, ""))?;
+    fs::create_dir_all(format!("{}/{slug}", blog_path!("/static/img/posts")))?;
+    Ok(dir)
+}
+
+#[test]
+fn test_slugify() {
+    assert_eq!(
+        slugify("Implement defer in Rust"),
+        "implement-defer-in-rust"
+    );
+    assert_eq!(
+        slugify("Rust 中的各种 String（一）"),
+        "rust-zhong-de-ge-chong-string-yi"
+    );
+    assert_eq!(slugify("  --  "), "");
+}
+
+#[test]
+fn test_new_post_slug() {
+    use crate::test_site;
+
+    let _site = test_site::site();
+    for slug in ["../escape", "a/b", "a\\b", "..", "", "空格 slug"] {
+        assert!(
+            new_post("Title", &[], Some(slug.to_string())).is_err(),
+            "{slug}"
+        );
+    }
+    assert!(!post_dir("../escape").exists());
+    let dir = new_post("Title", &[], Some("retain_mut-Vec2".to_string())).unwrap();
+    assert_eq!(dir, post_dir("retain_mut-Vec2"));
+    assert!(dir.join(POST_MD).is_file());
+}
@@ -1,14 +1,43 @@
-use crate::{BLOG_PATH, blog_path, errors::SearchError, lock::Lock, timestamp::TimeStamp};
+use crate::{
+    blog_path, cleaner::ReadingStats, config::CONFIG, errors::SearchError, lock::Lock,
+    timestamp::TimeStamp,
+};
+use chrono::{DateTime, FixedOffset};
 use comrak::options::{Extension, Render};
 use ignore::{WalkBuilder, types::TypesBuilder};
+use percent_encoding::percent_decode_str;
+use regex::Regex;
 use serde::{Deserialize, Serialize};
 use std::{
     collections::HashMap,
     fs,
-    path::PathBuf,
+    ops::Range,
+    path::{Component, Path, PathBuf},
     sync::{Arc, LazyLock},
 };
 
+/// An `<img>` tag written as raw html
+pub static IMG_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?i)<img\b[^>]*>").expect("valid regex"));
+/// The `src` of an [`IMG_RE`] match
+pub static SRC_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r#"(?i)\bsrc\s*=\s*["']([^"']*)["']"#).expect("valid regex"));
+
+/// The file under `blog/static` a `/static/...` url points to,
+/// none if the decoded path would leave that folder
+pub fn static_file(url: &str) -> Option<PathBuf> {
+    let path = url.split(['?', '#']).next()?.strip_prefix("/static/")?;
+    let decoded = percent_decode_str(path).decode_utf8_lossy();
+    let path = Path::new(decoded.as_ref());
+    if !path
+        .components()
+        .all(|component| matches!(component, Component::Normal(_)))
+    {
+        return None;
+    }
+    Some(PathBuf::from(blog_path!("/static")).join(path))
+}
+
 pub static MD_OPTIONS: LazyLock<comrak::Options> = LazyLock::new(|| comrak::Options {
     extension: Extension {
         table: true,
@@ -28,9 +57,87 @@ pub static MD_OPTIONS: LazyLock<comrak::Options> = LazyLock::new(|| comrak::Opti
     ..Default::default()
 });
 
+pub const POST_MD: &str = "post.md";
+pub const POST_FRONTMATTER: &str = "post_frontmatter.toml";
+/// Delimits the frontmatter embedded at the top of `post.md`
+pub const FRONTMATTER_DELIMITER: &str = "+++";
+
+pub fn post_dir(post_name: &str) -> PathBuf {
+    PathBuf::from(format!("{}/posts/{}", CONFIG.paths.blog, post_name))
+}
+
+/// The markdown of a post, without the embedded frontmatter
 pub fn extract_md(post_name: &str) -> Result<String, SearchError> {
-    let s = fs::read_to_string(format!("{}/posts/{}/post.md", BLOG_PATH, post_name))?;
-    Ok(s)
+    let s = fs::read_to_string(post_dir(post_name).join(POST_MD))?;
+    Ok(strip_frontmatter(&s).to_string())
+}
+
+/// Position of the `+++`-delimited TOML at the top of a `post.md`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EmbeddedFrontMatter {
+    /// the TOML between the delimiters
+    pub toml: Range<usize>,
+    /// the markdown after the closing delimiter
+    pub body_start: usize,
+}
+
+pub fn find_embedded_frontmatter(md: &str) -> Option<EmbeddedFrontMatter> {
+    let (opening, rest) = md.split_once('\n')?;
+    if opening.trim_end() != FRONTMATTER_DELIMITER {
+        return None;
+    }
+    let toml_start = opening.len() + 1;
+    let mut pos = toml_start;
+    for line in rest.split_inclusive('\n') {
+        if line.trim_end() == FRONTMATTER_DELIMITER {
+            return Some(EmbeddedFrontMatter {
+                toml: toml_start..pos,
+                body_start: pos + line.len(),
+            });
+        }
+        pos += line.len();
+    }
+    None
+}
+
+/// The markdown after the embedded frontmatter, if any
+pub fn strip_frontmatter(md: &str) -> &str {
+    match find_embedded_frontmatter(md) {
+        Some(embedded) => &md[embedded.body_start..],
+        None => md,
+    }
+}
+
+/// A `post.md` with the frontmatter at its top
+pub fn embed_frontmatter(toml: &str, md: &str) -> String {
+    let newline = if toml.ends_with('\n') { "" } else { "\n" };
+    format!("{FRONTMATTER_DELIMITER}\n{toml}{newline}{FRONTMATTER_DELIMITER}\n\n{md}")
+}
+
+/// The file holding the frontmatter of a post, `post_frontmatter.toml` takes precedence
+pub fn frontmatter_path(post_name: &str) -> PathBuf {
+    let dir = post_dir(post_name);
+    let toml = dir.join(POST_FRONTMATTER);
+    if toml.exists() {
+        toml
+    } else {
+        dir.join(POST_MD)
+    }
+}
+
+/// The TOML of a path from [`find_all_frontmatter_paths`]
+pub fn read_frontmatter(path: &Path) -> Result<String, SearchError> {
+    let content = fs::read_to_string(path)?;
+    if !path.ends_with(POST_MD) {
+        return Ok(content);
+    }
+    match find_embedded_frontmatter(&content) {
+        Some(embedded) => Ok(content[embedded.toml].to_string()),
+        None => Err(SearchError::internal(format!(
+            "No frontmatter in '{}'",
+            path.display()
+        ))),
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -42,42 +149,104 @@ pub struct FrontMatter {
     pub updated: TimeStamp,
     pub tags: Vec<String>,
     pub author: String,
-    pub estimated_reading_time: u32,
+    /// minutes, computed from the post when it's absent
+    pub estimated_reading_time: Option<u32>,
+    /// `estimated_reading_time`, or the one computed from the post
+    #[serde(skip_deserializing)]
+    pub reading_time: u32,
+    /// CJK characters and Latin words of the post, code excluded
+    #[serde(skip_deserializing)]
+    pub word_count: usize,
     pub cover_image: Option<String>,
+    /// show the table of contents beside the post
+    #[serde(default = "default_toc")]
+    pub toc: bool,
+    /// drafts are only reachable through the preview url
+    #[serde(default)]
+    pub draft: bool,
+    /// keep the post hidden until this time, `posted` is used when it's absent
+    pub publish_at: Option<TimeStamp>,
+    /// name of the multi-part series the post belongs to
+    pub series: Option<String>,
+    /// position in the series, parts without it go last in `posted` order
+    pub series_order: Option<u32>,
+}
+
+fn default_toc() -> bool {
+    true
+}
+
+impl FrontMatter {
+    /// When the post is due, `publish_at` or else `posted`
+    pub fn publish_time(&self) -> DateTime<FixedOffset> {
+        *self.publish_at.unwrap_or(self.posted)
+    }
+
+    /// Whether the post is due to be listed, indexed and served
+    pub fn is_published(&self) -> bool {
+        self.is_published_at(chrono::Utc::now())
+    }
+
+    /// [`FrontMatter::is_published`] at `now`
+    pub fn is_published_at(&self, now: chrono::DateTime<chrono::Utc>) -> bool {
+        !self.draft && self.publish_time() <= now
+    }
+
+    /// Fill in the fields computed from the markdown of the post
+    fn compute_stats(&mut self, md: &str) {
+        let stats = ReadingStats::new(md);
+        self.word_count = stats.word_count();
+        self.reading_time = self
+            .estimated_reading_time
+            .unwrap_or_else(|| stats.minutes());
+    }
 }
 
 pub fn find_all_frontmatters() -> Result<Vec<FrontMatter>, SearchError> {
-    let mut t = TypesBuilder::new();
-    t.add_defaults();
-    let toml = t.select("toml").build()?;
-    let file_walker = WalkBuilder::new(blog_path!("/posts")).types(toml).build();
     let mut frontmatters = Vec::new();
-    for entry in file_walker {
-        let entry = entry?;
-        let path = entry.path();
-        if path.is_file() {
-            let content = fs::read_to_string(path)?;
-            let fm: FrontMatter = toml::from_str(&content)?;
-            frontmatters.push(fm);
-        }
+    for path in find_all_frontmatter_paths()? {
+        let content = read_frontmatter(&path)?;
+        let mut fm: FrontMatter = toml::from_str(&content)?;
+        let md = fs::read_to_string(path.with_file_name(POST_MD))?;
+        fm.compute_stats(strip_frontmatter(&md));
+        frontmatters.push(fm);
     }
     Ok(frontmatters)
 }
 
+/// Every `post_frontmatter.toml`, and every `post.md` embedding the frontmatter instead
+pub fn find_all_frontmatter_paths() -> Result<Vec<PathBuf>, SearchError> {
+    let mut paths = find_all_paths("toml")?;
+    for path in find_all_post_paths()? {
+        if path.ends_with(POST_MD) && !path.with_file_name(POST_FRONTMATTER).exists() {
+            let content = fs::read_to_string(&path)?;
+            if find_embedded_frontmatter(&content).is_some() {
+                paths.push(path);
+            }
+        }
+    }
+    Ok(paths)
+}
+
 pub fn find_all_post_paths() -> Result<Vec<PathBuf>, SearchError> {
+    find_all_paths("md")
+}
+
+/// Files of a type under the posts folder
+fn find_all_paths(file_type: &str) -> Result<Vec<PathBuf>, SearchError> {
     let mut t = TypesBuilder::new();
     t.add_defaults();
-    let md = t.select("md").build()?;
-    let file_walker = WalkBuilder::new(blog_path!("/posts")).types(md).build();
-    let mut mds = Vec::new();
+    let types = t.select(file_type).build()?;
+    let file_walker = WalkBuilder::new(blog_path!("/posts")).types(types).build();
+    let mut paths = Vec::new();
     for entry in file_walker {
         let entry = entry?;
         let path = entry.path();
         if path.is_file() {
-            mds.push(path.to_path_buf());
+            paths.push(path.to_path_buf());
         }
     }
-    Ok(mds)
+    Ok(paths)
 }
 
 pub fn extract_frontmatter(post_name: &str) -> Result<Arc<FrontMatter>, SearchError> {
@@ -112,3 +281,14 @@ pub fn initial_fm() -> Result<HashMap<String, Arc<FrontMatter>>, SearchError> {
         .collect::<HashMap<_, _>>();
     Ok(map)
 }
+
+#[test]
+fn test_static_file() {
+    let _site = crate::test_site::site();
+    let file = static_file("/static/img/a%20b.png?x=1#top").unwrap();
+    assert!(file.ends_with("static/img/a b.png"));
+    assert_eq!(static_file("/static/%2e%2e/src/lib.rs"), None);
+    assert_eq!(static_file("/static/img/../../Cargo.toml"), None);
+    assert_eq!(static_file("/static/%2Fetc/passwd"), None);
+    assert_eq!(static_file("/img/a.png"), None);
+}
@@ -1,5 +1,7 @@
 use crate::{
-    INDEX_DIR, STOP_WORDS,
+    STOP_WORDS,
+    build_index::{check_index, post_text},
+    config::{CONFIG, IndexReload},
     errors::SearchError,
     jieba::{self, JIEBA_ANALYZER, JIEBA_ANALYZER_SEARCH},
     post::{FrontMatter, extract_frontmatter},
@@ -8,17 +10,20 @@ use rayon::iter::{IntoParallelIterator, ParallelIterator};
 use regex::Regex;
 use serde::Serialize;
 use std::{
-    collections::HashSet,
-    sync::{Arc, LazyLock},
+    collections::{HashMap, HashSet},
+    path::Path,
+    sync::{Arc, LazyLock, PoisonError, RwLock},
     time::{Duration, Instant},
 };
 use tantivy::{
-    Index, IndexReader, TantivyDocument, Term,
+    Index, IndexReader, ReloadPolicy, Searcher, TantivyDocument, Term,
     collector::TopDocs,
-    query::{BooleanQuery, BoostQuery, Occur, Query, TermQuery},
-    schema::{Facet, IndexRecordOption, Value},
+    query::{BooleanQuery, BoostQuery, MoreLikeThisQuery, Occur, Query, TermQuery},
+    schema::{Facet, IndexRecordOption, OwnedValue, Value},
     snippet::SnippetGenerator,
-    tokenizer::{LowerCaser, RemoveLongFilter, Stemmer, StopWordFilter, TextAnalyzer},
+    tokenizer::{
+        LowerCaser, RemoveLongFilter, Stemmer, StopWordFilter, TextAnalyzer, TokenStream, Tokenizer,
+    },
 };
 
 #[derive(Debug, Serialize)]
@@ -28,6 +33,13 @@ pub struct SearchTerm {
     pub snippet: String,
 }
 
+#[derive(Debug, Serialize)]
+pub struct Suggestion {
+    pub text: String,
+    /// number of documents containing the completed term
+    pub doc_freq: u32,
+}
+
 #[derive(Debug)]
 pub struct SearchResult<T> {
     pub count: usize,
@@ -64,14 +76,47 @@ pub fn is_cjk_or_en(s: &str) -> bool {
     RE.is_match(s)
 }
 
-// Use lazy static to avoid reopening the index and reloading reader every time
+// Opened once and reused, the reader is swapped as a whole by `reload_index`,
+// so a search in flight keeps the searcher it started with
+static READER: RwLock<Option<IndexReader>> = RwLock::new(None);
+
+fn open_reader() -> Result<IndexReader, SearchError> {
+    // callers must not panic on a missing index
+    if !Path::new(&CONFIG.paths.index).join("meta.json").exists() {
+        return Err(SearchError::internal("The search index has not been built"));
+    }
+    let index = Index::open_in_dir(&CONFIG.paths.index)?;
+    check_index(&index)?;
+    jieba::register_tokenizers(&index);
+    let policy = match CONFIG.server.index_reload {
+        IndexReload::OnCommit => ReloadPolicy::OnCommitWithDelay,
+        IndexReload::Manual => ReloadPolicy::Manual,
+    };
+    Ok(index.reader_builder().reload_policy(policy).try_into()?)
+}
+
+/// Reopen the index from its folder, so that a rebuilt one is searched from now on
+pub fn reload_index() -> Result<(), SearchError> {
+    let reader = open_reader()?;
+    *READER.write().unwrap_or_else(PoisonError::into_inner) = Some(reader);
+    Ok(())
+}
 
-static INDEX: LazyLock<Index> = LazyLock::new(|| {
-    let index = Index::open_in_dir(INDEX_DIR).unwrap();
-    index.tokenizers().register("jieba", JIEBA_ANALYZER.clone());
-    index
-});
-static READER: LazyLock<IndexReader> = LazyLock::new(|| INDEX.reader().unwrap());
+/// A consistent view of the index, it's opened on first use
+fn searcher() -> Result<Searcher, SearchError> {
+    if let Some(reader) = READER
+        .read()
+        .unwrap_or_else(PoisonError::into_inner)
+        .as_ref()
+    {
+        return Ok(reader.searcher());
+    }
+    let mut reader = READER.write().unwrap_or_else(PoisonError::into_inner);
+    if reader.is_none() {
+        *reader = Some(open_reader()?);
+    }
+    Ok(reader.as_ref().expect("opened above").searcher())
+}
 
 pub fn search_index(
     query_text: &str,
@@ -81,13 +126,13 @@ pub fn search_index(
 ) -> Result<SearchResult<SearchTerm>, SearchError> {
     let instant_sum = Instant::now();
 
-    let schema = INDEX.schema();
+    let searcher = searcher()?;
+    let schema = searcher.schema();
 
     let content = schema.get_field("content_zh")?;
     let title_field = schema.get_field("title")?;
     let path_field = schema.get_field("path")?;
 
-    let searcher = READER.searcher();
     log::info!("{:?}", instant_sum.elapsed());
 
     let mut jieba_analyzer = JIEBA_ANALYZER.clone();
@@ -265,3 +310,176 @@ pub fn search_index(
     };
     Ok(search_result)
 }
+
+/// Find the posts most similar to `fm` by content, boosted by the tags they share.
+///
+/// Returns the file names of published posts, best match first.
+pub fn related_posts(fm: &FrontMatter, limit: usize) -> Result<Vec<String>, SearchError> {
+    // post pages must not depend on the index being built
+    let searcher = searcher()?;
+    let schema = searcher.schema();
+    let content = schema.get_field("content_zh")?;
+    let tag_facet = schema.get_field("tags")?;
+    let path_field = schema.get_field("path")?;
+
+    let text = post_text(fm)?;
+    let more_like_this = MoreLikeThisQuery::builder()
+        .with_min_doc_frequency(2)
+        .with_min_term_frequency(2)
+        .with_min_word_length(2)
+        .with_max_query_terms(30)
+        .with_stop_words(STOP_WORDS.iter().cloned().collect())
+        .with_document_fields(vec![(content, vec![OwnedValue::Str(text)])]);
+    let mut clauses: Vec<(Occur, Box<dyn Query>)> = vec![(Occur::Should, Box::new(more_like_this))];
+    for tag in fm.tags.iter() {
+        let facet = Facet::from(&format!("/{}", tag.to_lowercase()));
+        let term = Term::from_facet(tag_facet, &facet);
+        let tag_query = TermQuery::new(term, IndexRecordOption::Basic);
+        clauses.push((
+            Occur::Should,
+            Box::new(BoostQuery::new(Box::new(tag_query), 2.0)),
+        ));
+    }
+    let query = BooleanQuery::from(clauses);
+
+    // the post itself is usually the best match
+    let top_docs = searcher.search(&query, &TopDocs::with_limit(limit + 1))?;
+    let mut related = Vec::with_capacity(limit);
+    for (_score, doc_addr) in top_docs {
+        let doc: TantivyDocument = searcher.doc(doc_addr)?;
+        let file_name = doc
+            .get_first(path_field)
+            .and_then(|v| v.as_str())
+            .ok_or(SearchError::internal("Can not get file name"))?;
+        if file_name == fm.file_name
+            || !extract_frontmatter(file_name).is_ok_and(|f| f.is_published())
+        {
+            continue;
+        }
+        related.push(file_name.to_string());
+        if related.len() == limit {
+            break;
+        }
+    }
+    Ok(related)
+}
+
+/// Complete the last word of `text` with the words of the titles and contents,
+/// the most frequent first.
+///
+/// The words are split by jieba, so unfinished Chinese is completed from its last word,
+/// e.g. `搜索引` from the terms starting with `引`, and the words before it are kept.
+pub fn suggest_terms(text: &str, limit: usize) -> Result<Vec<Suggestion>, SearchError> {
+    let searcher = searcher()?;
+    let suggest = searcher.schema().get_field("suggest")?;
+
+    // the unfinished word may be any token reaching the end, short or long
+    let mut tokenizer = jieba::JiebaTokenizer::with_mode(jieba::JiebaMode::Search);
+    let mut token_stream = tokenizer.token_stream(text);
+    let mut starts = Vec::new();
+    while let Some(token) = token_stream.next() {
+        if token.offset_to == text.len() && is_cjk_or_en(&token.text) {
+            starts.push(token.offset_from);
+        }
+    }
+
+    let mut completions = HashMap::<String, u32>::new();
+    for start in starts {
+        let (head, word) = text.split_at(start);
+        let word = word.to_lowercase();
+        // doc_freq is counted per segment, the index is merged without deleted documents
+        let mut doc_freqs = HashMap::<String, u32>::new();
+        for segment_reader in searcher.segment_readers() {
+            let inverted_index = segment_reader.inverted_index(suggest)?;
+            let mut terms = inverted_index.terms().range().ge(&word).into_stream()?;
+            while terms.advance() {
+                if !terms.key().starts_with(word.as_bytes()) {
+                    break;
+                }
+                let Ok(term) = std::str::from_utf8(terms.key()) else {
+                    continue;
+                };
+                if chars_bigger_than(term, 1) && is_cjk_or_en(term) && !is_stop_word(term) {
+                    *doc_freqs.entry(term.to_string()).or_default() += terms.value().doc_freq;
+                }
+            }
+        }
+        // `搜索引擎` completes both `搜索引` and `引`, it's counted once
+        for (term, doc_freq) in doc_freqs {
+            let freq = completions.entry(format!("{head}{term}")).or_default();
+            *freq = (*freq).max(doc_freq);
+        }
+    }
+    let mut suggestions = completions
+        .into_iter()
+        .map(|(text, doc_freq)| Suggestion { text, doc_freq })
+        .collect::<Vec<_>>();
+    suggestions.sort_by(|a, b| {
+        b.doc_freq
+            .cmp(&a.doc_freq)
+            .then_with(|| a.text.cmp(&b.text))
+    });
+    suggestions.truncate(limit);
+    Ok(suggestions)
+}
+
+#[test]
+fn test_suggest_terms() {
+    use crate::{build_index::build_index, test_site};
+
+    let _site = test_site::site();
+    test_site::write_post(
+        "a",
+        "Languages",
+        "",
+        "A language and its compiler, 搜索引擎",
+    );
+    test_site::write_post("b", "B", "", "The language server of the 搜索引擎");
+    build_index().unwrap();
+    reload_index().unwrap();
+
+    let texts = |text| {
+        suggest_terms(text, 10)
+            .unwrap()
+            .into_iter()
+            .map(|suggestion| (suggestion.text, suggestion.doc_freq))
+            .collect::<Vec<_>>()
+    };
+    // the words as written, not their stems
+    assert_eq!(
+        texts("rust lang"),
+        [
+            ("rust language".to_string(), 2),
+            ("rust languages".to_string(), 1)
+        ]
+    );
+    assert!(texts("搜索引").contains(&("搜索引擎".to_string(), 2)));
+    assert!(texts("zzz").is_empty());
+}
+
+#[test]
+fn test_reload_index() {
+    use crate::{build_index::build_index, test_site};
+    use std::fs;
+
+    let _site = test_site::site();
+    test_site::write_post("a", "A", "", "first post");
+    build_index().unwrap();
+    reload_index().unwrap();
+    let before = searcher().unwrap();
+    assert_eq!(before.num_docs(), 1);
+
+    // the index is rebuilt from scratch
+    fs::remove_dir_all(&CONFIG.paths.index).unwrap();
+    test_site::write_post("b", "B", "", "second post");
+    build_index().unwrap();
+    reload_index().unwrap();
+    assert_eq!(searcher().unwrap().num_docs(), 2);
+    // a search in flight keeps the index it started with
+    assert_eq!(before.num_docs(), 1);
+
+    // a missing index is reported, the opened one is still searched
+    fs::remove_dir_all(&CONFIG.paths.index).unwrap();
+    assert!(reload_index().is_err());
+    assert_eq!(searcher().unwrap().num_docs(), 2);
+}
@@ -0,0 +1,82 @@
+//! A site in a temporary folder, `CONFIG` of the tests points to it, and the
+//! frontmatters of its posts
+
+use crate::post::{FrontMatter, POST_MD, embed_frontmatter, post_dir};
+use std::{
+    env, fs,
+    path::PathBuf,
+    sync::{LazyLock, Mutex, MutexGuard, PoisonError},
+};
+
+static ROOT: LazyLock<PathBuf> = LazyLock::new(|| {
+    let root = env::temp_dir().join(format!("search_utils-test-{}", std::process::id()));
+    let vars = [
+        ("BLOG_CONFIG", root.join("site.toml")),
+        ("BLOG_PATHS_BLOG", root.join("blog")),
+        ("BLOG_PATHS_INDEX", root.join("index")),
+        (
+            "BLOG_PATHS_CONTENT_HASHES",
+            root.join("content_hashes.toml"),
+        ),
+        (
+            "BLOG_PATHS_SEARCH",
+            PathBuf::from(env!("CARGO_MANIFEST_DIR")),
+        ),
+    ];
+    for (key, value) in vars {
+        // SAFETY: the tests reading the config go through `site`, which holds the lock
+        unsafe { env::set_var(key, value) };
+    }
+    root
+});
+
+static LOCK: Mutex<()> = Mutex::new(());
+
+/// An empty site, without posts, index or hashes, for as long as the guard lives
+pub fn site() -> MutexGuard<'static, ()> {
+    let guard = LOCK.lock().unwrap_or_else(PoisonError::into_inner);
+    let root = &*ROOT;
+    if root.exists() {
+        fs::remove_dir_all(root).unwrap();
+    }
+    fs::create_dir_all(root.join("blog/posts")).unwrap();
+    fs::create_dir_all(root.join("blog/static")).unwrap();
+    guard
+}
+
+/// The frontmatter of a published post, the fields of `extra` are added or replace the
+/// default ones
+pub fn frontmatter_toml(name: &str, extra: &str) -> String {
+    let mut table = toml::Table::new();
+    let fields = [
+        ("title", name),
+        ("file_name", name),
+        ("description", ""),
+        ("posted", "2025-01-01T00:00:00+08:00"),
+        ("updated", "2025-01-01T00:00:00+08:00"),
+        ("author", "lhz07"),
+    ];
+    for (key, value) in fields {
+        table.insert(key.to_string(), value.into());
+    }
+    table.insert("tags".to_string(), vec!["Rust"].into());
+    table.extend(extra.parse::<toml::Table>().unwrap());
+    table.to_string()
+}
+
+/// [`frontmatter_toml`] parsed
+pub fn frontmatter(name: &str, extra: &str) -> FrontMatter {
+    toml::from_str(&frontmatter_toml(name, extra)).unwrap()
+}
+
+/// Write a published post, `extra` is added to its frontmatter
+pub fn write_post(name: &str, title: &str, extra: &str, body: &str) {
+    let extra = format!("title = {}\n{extra}", toml::Value::from(title));
+    let dir = post_dir(name);
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(
+        dir.join(POST_MD),
+        embed_frontmatter(&frontmatter_toml(name, &extra), body),
+    )
+    .unwrap();
+}
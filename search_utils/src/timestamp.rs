@@ -1,4 +1,5 @@
-use chrono::{DateTime, FixedOffset};
+use crate::{config::CONFIG, errors::SearchError};
+use chrono::{DateTime, FixedOffset, SecondsFormat, Utc};
 use serde::{Deserialize, Serialize};
 use std::{fmt, ops::Deref};
 
@@ -27,3 +28,15 @@ impl Serialize for TimeStamp {
         serializer.serialize_str(&self.to_string())
     }
 }
+
+/// The current time in `site.timezone`, formatted like `posted` and `updated`
+pub fn now_rfc3339() -> Result<String, SearchError> {
+    let offset = CONFIG
+        .site
+        .timezone
+        .parse::<FixedOffset>()
+        .map_err(|e| SearchError::internal(format!("Invalid timezone in the config: {e}")))?;
+    Ok(Utc::now()
+        .with_timezone(&offset)
+        .to_rfc3339_opts(SecondsFormat::Secs, false))
+}
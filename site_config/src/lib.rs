@@ -0,0 +1,255 @@
+//! The site config, shared by the blog, its search tools and auto_builder
+
+use serde::{Deserialize, Serialize};
+use std::{borrow::Cow, fs, io, sync::LazyLock};
+use toml::{Table, Value};
+
+/// The config file, relative to the working directory unless set by this env var
+pub const CONFIG_PATH_ENV: &str = "BLOG_CONFIG";
+const DEFAULT_CONFIG_PATH: &str = "./site.toml";
+/// Every field can be overridden by `BLOG_<SECTION>_<KEY>`, e.g. `BLOG_SITE_URL`
+const ENV_PREFIX: &str = "BLOG";
+
+pub static CONFIG: LazyLock<Config> = LazyLock::new(|| match Config::load() {
+    Ok(config) => config,
+    Err(e) => {
+        // the logger may not be initialized yet
+        eprintln!("Can not load the site config: {e}");
+        std::process::exit(1);
+    }
+});
+
+#[derive(Debug, thiserror::Error)]
+pub enum ConfigError {
+    #[error("IO error: {0}")]
+    IO(#[from] io::Error),
+    #[error("Toml parse error: {0}")]
+    TomlDe(#[from] toml::de::Error),
+    #[error("Toml serialize error: {0}")]
+    TomlSer(#[from] toml::ser::Error),
+    #[error("Invalid config: {0}")]
+    Invalid(Cow<'static, str>),
+}
+
+impl ConfigError {
+    pub fn invalid<S: Into<Cow<'static, str>>>(s: S) -> Self {
+        ConfigError::Invalid(s.into())
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub site: SiteConfig,
+    pub server: ServerConfig,
+    pub paths: PathsConfig,
+    pub pages: PagesConfig,
+    pub links: LinksConfig,
+    pub highlight: HighlightConfig,
+    pub dev: DevConfig,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SiteConfig {
+    pub title: String,
+    /// without a trailing slash
+    pub url: String,
+    /// author of new posts
+    pub author: String,
+    /// UTC offset of the timestamps of new posts, like `+08:00`
+    pub timezone: String,
+}
+
+impl Default for SiteConfig {
+    fn default() -> Self {
+        Self {
+            title: "lhz07's Blog".to_string(),
+            url: "https://lhz07.com".to_string(),
+            author: "lhz07".to_string(),
+            timezone: "+08:00".to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ServerConfig {
+    pub bind: String,
+    /// when a rebuilt search index is picked up, besides SIGHUP
+    pub index_reload: IndexReload,
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IndexReload {
+    /// shortly after every commit to the index
+    #[default]
+    OnCommit,
+    /// only on SIGHUP, or a reload by auto_builder in debug builds
+    Manual,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        let bind = if cfg!(debug_assertions) {
+            "0.0.0.0:8000"
+        } else {
+            "127.0.0.1:8000"
+        };
+        Self {
+            bind: bind.to_string(),
+            index_reload: IndexReload::default(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PathsConfig {
+    pub blog: String,
+    pub search: String,
+    pub index: String,
+    pub friend_requests: String,
+    /// content hashes of the posts, to bump `updated` when a post changes
+    pub content_hashes: String,
+}
+
+impl Default for PathsConfig {
+    fn default() -> Self {
+        Self {
+            blog: "./blog".to_string(),
+            search: "./search_utils".to_string(),
+            index: "./search_utils/search/data".to_string(),
+            friend_requests: "./friend_requests".to_string(),
+            content_hashes: "./search_utils/content_hashes.toml".to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PagesConfig {
+    pub posts_per_page: usize,
+    pub search_results_per_page: usize,
+}
+
+impl Default for PagesConfig {
+    fn default() -> Self {
+        Self {
+            posts_per_page: 5,
+            search_results_per_page: 7,
+        }
+    }
+}
+
+/// The link checker of `blog check-links`
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LinksConfig {
+    /// checks external links when set, `{url}` is replaced by the percent-encoded link
+    /// and a 2xx answer means the link is alive
+    pub external_checker: String,
+}
+
+/// Themes of code blocks, any of syntect's default themes
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct HighlightConfig {
+    /// also used by the inline styles of feeds
+    pub light_theme: String,
+    pub dark_theme: String,
+}
+
+impl Default for HighlightConfig {
+    fn default() -> Self {
+        Self {
+            light_theme: "InspiredGitHub".to_string(),
+            dark_theme: "base16-ocean.dark".to_string(),
+        }
+    }
+}
+
+/// Ports of auto_builder, which reloads the debug build
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DevConfig {
+    /// websocket telling the browser to reload
+    pub ws_port: u16,
+    /// socket telling the blog to reload its posts
+    pub socket_port: u16,
+}
+
+impl Default for DevConfig {
+    fn default() -> Self {
+        Self {
+            ws_port: 9001,
+            socket_port: 9002,
+        }
+    }
+}
+
+impl Config {
+    /// Read the config file if it exists, then apply the env overrides
+    pub fn load() -> Result<Self, ConfigError> {
+        let path =
+            std::env::var(CONFIG_PATH_ENV).unwrap_or_else(|_| DEFAULT_CONFIG_PATH.to_string());
+        let config = match fs::read_to_string(&path) {
+            Ok(content) => toml::from_str::<Config>(&content)?,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Config::default(),
+            Err(e) => return Err(e.into()),
+        };
+        // go through a table with every field filled in, so that any of them can be overridden
+        let Value::Table(mut table) = Value::try_from(&config)? else {
+            return Err(ConfigError::invalid("config is not a table"));
+        };
+        apply_env(&mut table, ENV_PREFIX)?;
+        let config = Value::Table(table).try_into::<Config>()?;
+        if config.pages.posts_per_page == 0 || config.pages.search_results_per_page == 0 {
+            return Err(ConfigError::invalid("page sizes must be greater than 0"));
+        }
+        Ok(config)
+    }
+}
+
+fn apply_env(table: &mut Table, prefix: &str) -> Result<(), ConfigError> {
+    for (key, value) in table.iter_mut() {
+        let name = format!("{prefix}_{}", key.to_uppercase());
+        if let Value::Table(table) = value {
+            apply_env(table, &name)?;
+            continue;
+        }
+        let Ok(var) = std::env::var(&name) else {
+            continue;
+        };
+        let invalid = || ConfigError::invalid(format!("{name}: invalid value '{var}'"));
+        *value = match value {
+            Value::Integer(_) => Value::Integer(var.parse().map_err(|_| invalid())?),
+            Value::Boolean(_) => Value::Boolean(var.parse().map_err(|_| invalid())?),
+            _ => Value::String(var.clone()),
+        };
+    }
+    Ok(())
+}
+
+#[test]
+fn test_env_overrides() {
+    let set = |key: &str, value: &str| {
+        // SAFETY: the only test of this crate, nothing else reads the environment
+        unsafe { std::env::set_var(key, value) }
+    };
+    set(CONFIG_PATH_ENV, "/nonexistent/site.toml");
+    set("BLOG_SERVER_BIND", "127.0.0.1:9000");
+    set("BLOG_SITE_URL", "https://staging.lhz07.com");
+    set("BLOG_PAGES_POSTS_PER_PAGE", "3");
+    let config = Config::load().unwrap();
+    assert_eq!(config.server.bind, "127.0.0.1:9000");
+    assert_eq!(config.site.url, "https://staging.lhz07.com");
+    assert_eq!(config.pages.posts_per_page, 3);
+    assert_eq!(config.paths.blog, PathsConfig::default().blog);
+
+    set("BLOG_PAGES_POSTS_PER_PAGE", "three");
+    assert!(matches!(Config::load(), Err(ConfigError::Invalid(_))));
+    set("BLOG_PAGES_POSTS_PER_PAGE", "0");
+    assert!(matches!(Config::load(), Err(ConfigError::Invalid(_))));
+}